@@ -0,0 +1,100 @@
+// Crane programs over tall stacks, comparing vectors, which move crates one by one, with
+// the treap ropes, which move whole runs. DAY5_BENCH_HEIGHT sets the initial height of the
+// stacks, 100 000 crates by default.
+use advent2022::day5::{CraneModel, Move, RopeStacks, Stacks};
+use criterion::{
+    black_box, criterion_group, criterion_main, measurement::WallTime, BatchSize, BenchmarkGroup,
+    BenchmarkId, Criterion,
+};
+
+const STACKS: usize = 9;
+
+/// Stacks of letters and random moves taking up to half of the crates of a stack.
+fn program(height: usize, moves: usize) -> (Stacks, Vec<Move>) {
+    let stacks = (0..STACKS)
+        .map(|stack| {
+            (0..height)
+                .map(|c| char::from(b'A' + ((stack + c) % 26) as u8).to_string())
+                .collect()
+        })
+        .collect();
+
+    let mut heights = [height; STACKS];
+    let mut seed = 0x2545_f491_4f6c_dd1du64;
+    let mut random = |bound: usize| {
+        seed = seed
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        (seed >> 33) as usize % bound
+    };
+    let program = (0..moves)
+        .map(|_| {
+            let from = random(STACKS);
+            let to = (from + 1 + random(STACKS - 1)) % STACKS;
+            let quantity = random(heights[from] / 2 + 1);
+            heights[from] -= quantity;
+            heights[to] += quantity;
+            Move::new(quantity, from + 1, to + 1)
+        })
+        .collect();
+    (Stacks::new(stacks), program)
+}
+
+fn bench_model(
+    group: &mut BenchmarkGroup<WallTime>,
+    model: &str,
+    crane: CraneModel,
+    stacks: &Stacks,
+    program: &[Move],
+) {
+    let moves = program.len();
+    // Vectors copy every crate moved, the largest programs are left to the ropes
+    if moves <= 10_000 {
+        let id = BenchmarkId::new(format!("vectors/{model}"), moves);
+        group.bench_function(id, |b| {
+            b.iter_batched(
+                || stacks.clone(),
+                |mut stacks| {
+                    for mv in black_box(program) {
+                        stacks.apply(crane, mv).unwrap();
+                    }
+                    stacks
+                },
+                BatchSize::LargeInput,
+            )
+        });
+    }
+    let rope = RopeStacks::new(stacks);
+    let id = BenchmarkId::new(format!("ropes/{model}"), moves);
+    group.bench_function(id, |b| {
+        b.iter_batched(
+            || rope.clone(),
+            |mut rope| {
+                for mv in black_box(program) {
+                    rope.apply(crane, mv).unwrap();
+                }
+                rope
+            },
+            BatchSize::LargeInput,
+        )
+    });
+}
+
+fn bench_cranes(c: &mut Criterion) {
+    let height = std::env::var("DAY5_BENCH_HEIGHT")
+        .ok()
+        .and_then(|height| height.parse().ok())
+        .unwrap_or(100_000);
+
+    let mut group = c.benchmark_group("day5");
+    group.sample_size(10);
+    for moves in [1_000, 10_000, 1_000_000] {
+        let (stacks, program) = program(height, moves);
+        bench_model(&mut group, "9000", CraneModel::Mover9000, &stacks, &program);
+        bench_model(&mut group, "9001", CraneModel::Mover9001, &stacks, &program);
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_cranes);
+criterion_main!(benches);
@@ -0,0 +1,69 @@
+// Marker search over large generated signals. The size defaults to 1 GiB and can be
+// lowered through DAY6_BENCH_BYTES. Run with `cargo +nightly bench --features simd` to
+// measure the vectorized bitmask search instead of the scalar one, along with
+// RUSTFLAGS="-C target-cpu=native" for it to use more than SSE2.
+use std::collections::HashSet;
+
+use advent2022::day6::{find_marker_bitmask, find_marker_counting};
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+const ALPHABET: &[u8] = b"abcdefghijklmnopqrstuvwxyz";
+
+type Search = fn(&[u8], usize) -> Option<usize>;
+
+/// The search as first written, one set of characters per window.
+fn find_marker_hashset(signal: &[u8], matching_chars: usize) -> Option<usize> {
+    let mut char_set: HashSet<u8> = HashSet::new();
+    for i in 0..(signal.len() + 1).saturating_sub(matching_chars) {
+        char_set.clear();
+        char_set.extend(signal[i..i + matching_chars].iter().copied());
+        if char_set.len() == matching_chars {
+            return Some(i + matching_chars);
+        }
+    }
+    None
+}
+
+/// Cycles through one letter less than a marker needs, so the only marker is the one
+/// closing the signal and the whole buffer gets searched.
+fn signal(len: usize, matching_chars: usize) -> Vec<u8> {
+    let mut signal = ALPHABET[..matching_chars - 1]
+        .iter()
+        .copied()
+        .cycle()
+        .take(len - matching_chars)
+        .collect::<Vec<u8>>();
+    signal.extend(&ALPHABET[..matching_chars]);
+    signal
+}
+
+fn bench_markers(c: &mut Criterion) {
+    let len = std::env::var("DAY6_BENCH_BYTES")
+        .ok()
+        .and_then(|bytes| bytes.parse().ok())
+        .unwrap_or(1 << 30);
+    let searches: [(&str, Search); 3] = [
+        ("hashset", find_marker_hashset),
+        ("counting", find_marker_counting),
+        ("bitmask", find_marker_bitmask),
+    ];
+
+    let mut group = c.benchmark_group("day6");
+    group.sample_size(10);
+    group.throughput(Throughput::Bytes(len as u64));
+    for matching_chars in [4, 14] {
+        let signal = signal(len, matching_chars);
+        for (name, search) in searches {
+            assert_eq!(search(&signal, matching_chars), Some(len));
+            group.bench_with_input(
+                BenchmarkId::new(name, matching_chars),
+                &signal,
+                |b, signal| b.iter(|| search(black_box(signal), matching_chars)),
+            );
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_markers);
+criterion_main!(benches);
@@ -0,0 +1,34 @@
+use std::{env, fs, io, process};
+
+use advent2022::day10::{assemble, disassemble, run_debugger, Crt};
+
+// Usage: day10_debugger [--disassemble] [program]
+fn main() {
+    let mut args = env::args().skip(1).peekable();
+    let disassemble_only = args.next_if(|arg| arg == "--disassemble").is_some();
+    let path = args
+        .next()
+        .unwrap_or_else(|| String::from("input/2022/day10.txt"));
+    let source = fs::read_to_string(&path).unwrap_or_else(|err| {
+        eprintln!("could not read {path}: {err}");
+        process::exit(1)
+    });
+
+    let program = match assemble(&source) {
+        Ok(program) => program,
+        Err(errors) => {
+            for error in errors {
+                eprintln!("{path}: {error}");
+            }
+            process::exit(1)
+        }
+    };
+
+    if disassemble_only {
+        print!("{}", disassemble(&program));
+        return;
+    }
+
+    run_debugger(&program, Crt::default(), io::stdin().lock(), io::stdout())
+        .expect("debugger session failed");
+}
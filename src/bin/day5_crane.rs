@@ -0,0 +1,42 @@
+use std::{env, fs, io, process};
+
+use advent2022::day5::{parse_input_generator, CraneModel, Simulator};
+
+fn operate(crane: CraneModel, input: &str, trace: bool) {
+    let (stacks, moves) = parse_input_generator(input);
+    let mut simulator = Simulator::new(crane, stacks);
+    let result = if trace {
+        simulator.run_traced(&moves, &mut io::stdout().lock())
+    } else {
+        simulator.run_traced(&moves, &mut io::sink())
+    };
+    if let Err(err) = result {
+        eprintln!("invalid move {err}");
+        process::exit(1)
+    }
+    if !trace {
+        println!("{}", simulator.stacks());
+    }
+    println!("{}", simulator.stacks().tops());
+}
+
+// Usage: day5_crane [--9000 | --9001] [--trace] [input]
+fn main() {
+    let mut args = env::args().skip(1).peekable();
+    let model = args
+        .next_if(|arg| arg == "--9000" || arg == "--9001")
+        .unwrap_or_else(|| String::from("--9000"));
+    let trace = args.next_if(|arg| arg == "--trace").is_some();
+    let path = args
+        .next()
+        .unwrap_or_else(|| String::from("input/2022/day5.txt"));
+
+    let input = fs::read_to_string(&path).unwrap_or_else(|err| {
+        eprintln!("could not read {path}: {err}");
+        process::exit(1)
+    });
+    match model.as_str() {
+        "--9001" => operate(CraneModel::Mover9001, &input, trace),
+        _ => operate(CraneModel::Mover9000, &input, trace),
+    }
+}
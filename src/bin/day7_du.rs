@@ -0,0 +1,79 @@
+use std::{env, fs, path::Path, process};
+
+use advent2022::day7::{
+    parse_folders, transcript_from_disk, DiskPlanner, DISK_CAPACITY, REQUIRED_FREE_SPACE,
+};
+
+// Usage: day7_du [--tree | --du | --folded | --plan] [transcript] [capacity] [required free space]
+//        day7_du --from-disk <directory>
+fn main() {
+    let mut args = env::args().skip(1).peekable();
+    let mode = args
+        .next_if(|arg| arg.starts_with("--"))
+        .unwrap_or_else(|| String::from("--tree"));
+    let path = args
+        .next()
+        .unwrap_or_else(|| String::from("input/2022/day7.txt"));
+    if mode == "--from-disk" {
+        match transcript_from_disk(Path::new(&path)) {
+            Ok(transcript) => print!("{transcript}"),
+            Err(err) => {
+                eprintln!("could not walk {path}: {err}");
+                process::exit(1)
+            }
+        }
+        return;
+    }
+
+    let transcript = fs::read_to_string(&path).unwrap_or_else(|err| {
+        eprintln!("could not read {path}: {err}");
+        process::exit(1)
+    });
+
+    let browsing_state = parse_folders(&transcript);
+    for diagnostic in browsing_state.diagnostics() {
+        eprintln!("{path}: {diagnostic}");
+    }
+
+    let tree = browsing_state.into_tree();
+    match mode.as_str() {
+        "--tree" => print!("{tree}"),
+        "--du" => print!("{}", tree.du_listing()),
+        "--folded" => print!("{}", tree.folded_stacks()),
+        "--plan" => {
+            let mut sizes = args.map(|arg| {
+                arg.parse::<usize>().unwrap_or_else(|_| {
+                    eprintln!("{arg} is not a size");
+                    process::exit(1)
+                })
+            });
+            let planner = DiskPlanner::new(
+                sizes.next().unwrap_or(DISK_CAPACITY),
+                sizes.next().unwrap_or(REQUIRED_FREE_SPACE),
+            );
+            println!("space to free: {}", planner.space_to_free(&tree));
+            let plans = [
+                ("smallest directory", planner.smallest_directory(&tree)),
+                ("optimal deletion", planner.optimal_deletion(&tree)),
+            ];
+            for (name, plan) in plans {
+                match plan {
+                    Some(deletion) => {
+                        let approximate = if deletion.exact { "" } else { " (approximate)" };
+                        println!("{name}: {} freed{approximate}", deletion.freed);
+                        for id in deletion.directories {
+                            println!("  {}", tree.path(id));
+                        }
+                    }
+                    None => println!("{name}: not enough space even deleting everything"),
+                }
+            }
+        }
+        _ => {
+            eprintln!(
+                "unknown mode {mode}, expected --tree, --du, --folded, --plan or --from-disk"
+            );
+            process::exit(1)
+        }
+    }
+}
@@ -0,0 +1,46 @@
+use std::{env, fs, path::Path, process};
+
+use advent2022::day8::Forest;
+
+// Usage: day8_heatmap [forest] [output directory]
+fn main() {
+    let mut args = env::args().skip(1);
+    let path = args
+        .next()
+        .unwrap_or_else(|| String::from("input/2022/day8.txt"));
+    let output_dir = args.next().unwrap_or_else(|| String::from("."));
+
+    let forest: Forest = fs::read_to_string(&path)
+        .unwrap_or_else(|err| {
+            eprintln!("could not read {path}: {err}");
+            process::exit(1)
+        })
+        .parse()
+        .unwrap_or_else(|err| {
+            eprintln!("{path}: {err}");
+            process::exit(1)
+        });
+
+    let (visible, scores) = forest.survey();
+    let exports = [
+        ("visibility.pgm", visible.to_pgm()),
+        ("visibility.csv", visible.to_csv()),
+        ("scenic.pgm", scores.to_pgm()),
+        ("scenic.ppm", scores.to_ppm()),
+        ("scenic.csv", scores.to_csv()),
+    ];
+    for (name, content) in exports {
+        let file = Path::new(&output_dir).join(name);
+        fs::write(&file, content).unwrap_or_else(|err| {
+            eprintln!("could not write {}: {err}", file.display());
+            process::exit(1)
+        });
+    }
+
+    if let Some((x, y)) = forest.best_tree() {
+        println!(
+            "best tree at x={x}, y={y} with a scenic score of {}",
+            scores.get(x, y).unwrap()
+        );
+    }
+}
@@ -1,27 +1,484 @@
+use std::fmt;
+use std::io::{self, BufRead, Write};
+use std::str::FromStr;
+
 use aoc_runner_derive::{aoc, aoc_generator};
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Instruction {
     Addx(i32),
     Noop,
 }
 
-impl From<&str> for Instruction {
-    fn from(input: &str) -> Self {
+impl Instruction {
+    pub fn cycles(&self) -> usize {
+        match self {
+            Instruction::Addx(_) => 2,
+            Instruction::Noop => 1,
+        }
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Instruction::Addx(v) => write!(f, "addx {v}"),
+            Instruction::Noop => write!(f, "noop"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AssemblyErrorKind {
+    UnknownOpcode(String),
+    MissingOperand(&'static str),
+    InvalidOperand(String),
+    UnexpectedOperand(String),
+}
+
+impl fmt::Display for AssemblyErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AssemblyErrorKind::UnknownOpcode(op) => write!(f, "unknown opcode `{op}`"),
+            AssemblyErrorKind::MissingOperand(op) => write!(f, "`{op}` expects an operand"),
+            AssemblyErrorKind::InvalidOperand(v) => write!(f, "`{v}` is not a valid integer"),
+            AssemblyErrorKind::UnexpectedOperand(v) => write!(f, "unexpected operand `{v}`"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AssemblyError {
+    pub line: usize,
+    pub kind: AssemblyErrorKind,
+}
+
+impl fmt::Display for AssemblyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.kind)
+    }
+}
+
+impl std::error::Error for AssemblyError {}
+
+impl FromStr for Instruction {
+    type Err = AssemblyErrorKind;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
         let mut split_input = input.split_whitespace();
-        match split_input
-            .next()
-            .expect(format!("nothing in line ? {input}").as_str())
-        {
-            "addx" => Instruction::Addx(
-                split_input
+        let instruction = match split_input.next().unwrap_or_default() {
+            "addx" => {
+                let operand = split_input
                     .next()
-                    .expect(format!("nothing after addx for line {}", input).as_str())
-                    .parse()
-                    .expect("could not parse"),
-            ),
+                    .ok_or(AssemblyErrorKind::MissingOperand("addx"))?;
+                Instruction::Addx(
+                    operand
+                        .parse()
+                        .map_err(|_| AssemblyErrorKind::InvalidOperand(operand.to_string()))?,
+                )
+            }
             "noop" => Instruction::Noop,
-            _ => unreachable!("Received unrecognized operation"),
+            op => return Err(AssemblyErrorKind::UnknownOpcode(op.to_string())),
+        };
+        match split_input.next() {
+            Some(extra) => Err(AssemblyErrorKind::UnexpectedOperand(extra.to_string())),
+            None => Ok(instruction),
+        }
+    }
+}
+
+/// Parses a whole program, skipping blank lines and reporting every invalid line
+/// with its 1-based line number.
+pub fn assemble(source: &str) -> Result<Vec<Instruction>, Vec<AssemblyError>> {
+    let mut program = vec![];
+    let mut errors = vec![];
+    for (line_index, line) in source.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        match line.parse() {
+            Ok(instruction) => program.push(instruction),
+            Err(kind) => errors.push(AssemblyError {
+                line: line_index + 1,
+                kind,
+            }),
+        }
+    }
+    if errors.is_empty() {
+        Ok(program)
+    } else {
+        Err(errors)
+    }
+}
+
+/// Pretty prints a program, annotating each instruction with the cycles it spans
+/// and the value of X before and after it.
+pub fn disassemble(program: &[Instruction]) -> String {
+    let mut output = String::new();
+    let (mut cycle, mut x) = (1, 1);
+    for instruction in program {
+        let cycles = match instruction.cycles() {
+            1 => format!("{cycle}"),
+            n => format!("{cycle}-{}", cycle + n - 1),
+        };
+        let registers = match instruction {
+            Instruction::Addx(v) => format!("X = {x} -> {}", x + v),
+            Instruction::Noop => format!("X = {x}"),
+        };
+        output.push_str(&format!(
+            "cycle {cycles:<9} {:<10} ; {registers}\n",
+            instruction.to_string()
+        ));
+        cycle += instruction.cycles();
+        if let Instruction::Addx(v) = instruction {
+            x += v;
+        }
+    }
+    output
+}
+
+/// State of the CPU during a single cycle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Tick {
+    pub cycle: usize,
+    pub x: i32,
+    pub pc: usize,
+    pub instruction: Instruction,
+}
+
+/// Screen drawn by the CPU, one pixel per cycle, left to right and top to bottom.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Crt {
+    width: usize,
+    height: usize,
+    sprite_width: usize,
+    pixels: Vec<bool>,
+}
+
+impl Default for Crt {
+    fn default() -> Self {
+        Crt::new(40, 6)
+    }
+}
+
+impl Crt {
+    pub fn new(width: usize, height: usize) -> Self {
+        Crt {
+            width,
+            height,
+            sprite_width: 3,
+            pixels: vec![false; width * height],
+        }
+    }
+
+    pub fn with_sprite_width(mut self, sprite_width: usize) -> Self {
+        self.sprite_width = sprite_width;
+        self
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Row and column of the pixel drawn during `cycle`, if it falls on the screen.
+    pub fn position(&self, cycle: usize) -> Option<(usize, usize)> {
+        let index = cycle.checked_sub(1)?;
+        if self.width == 0 || index >= self.width * self.height {
+            return None;
+        }
+        Some((index / self.width, index % self.width))
+    }
+
+    /// Whether the sprite, centered on `x`, covers `column`.
+    pub fn sprite_covers(&self, column: usize, x: i32) -> bool {
+        let left = x - (self.sprite_width as i32 - 1) / 2;
+        (left..left + self.sprite_width as i32).contains(&(column as i32))
+    }
+
+    /// Draws the pixel of `cycle`, returning whether it is lit, or `None` once off screen.
+    pub fn draw(&mut self, cycle: usize, x: i32) -> Option<bool> {
+        let (row, column) = self.position(cycle)?;
+        let lit = self.sprite_covers(column, x);
+        self.pixels[row * self.width + column] = lit;
+        Some(lit)
+    }
+
+    pub fn is_lit(&self, row: usize, column: usize) -> bool {
+        self.pixels[row * self.width + column]
+    }
+
+    pub fn rows(&self) -> Vec<String> {
+        self.pixels
+            .chunks(self.width.max(1))
+            .map(|row| row.iter().map(|&lit| if lit { '#' } else { '.' }).collect())
+            .collect()
+    }
+}
+
+impl fmt::Display for Crt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.rows().join("\n"))
+    }
+}
+
+/// Cycle-accurate CPU, used by the debugger to step through a program.
+pub struct Cpu<'a> {
+    program: &'a [Instruction],
+    pc: usize,
+    elapsed: usize,
+    cycle: usize,
+    x: i32,
+}
+
+impl<'a> Cpu<'a> {
+    pub fn new(program: &'a [Instruction]) -> Self {
+        Cpu {
+            program,
+            pc: 0,
+            elapsed: 0,
+            cycle: 0,
+            x: 1,
+        }
+    }
+
+    pub fn x(&self) -> i32 {
+        self.x
+    }
+
+    pub fn cycle(&self) -> usize {
+        self.cycle
+    }
+
+    pub fn pc(&self) -> usize {
+        self.pc
+    }
+
+    pub fn is_halted(&self) -> bool {
+        self.pc >= self.program.len()
+    }
+
+    /// Runs one cycle, returning the state seen during it, or `None` once the program is over.
+    pub fn step(&mut self) -> Option<Tick> {
+        let instruction = *self.program.get(self.pc)?;
+        self.cycle += 1;
+        let tick = Tick {
+            cycle: self.cycle,
+            x: self.x,
+            pc: self.pc,
+            instruction,
+        };
+        self.elapsed += 1;
+        if self.elapsed == instruction.cycles() {
+            if let Instruction::Addx(v) = instruction {
+                self.x += v;
+            }
+            self.pc += 1;
+            self.elapsed = 0;
+        }
+        Some(tick)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Register {
+    X,
+    Cycle,
+    Pc,
+    Crt,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    Step(usize),
+    Run,
+    RunUntilCycle(usize),
+    Print(Register),
+    BreakWhen(i32),
+    ClearBreakpoints,
+    Help,
+    Quit,
+}
+
+impl FromStr for Command {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        fn parse_number<T: FromStr>(word: &str) -> Result<T, String> {
+            word.parse()
+                .map_err(|_| format!("`{word}` is not a valid number"))
+        }
+
+        let words = input.split_whitespace().collect::<Vec<&str>>();
+        match words.as_slice() {
+            ["step" | "s"] => Ok(Command::Step(1)),
+            ["step" | "s", n] => Ok(Command::Step(parse_number(n)?)),
+            ["run" | "r" | "continue" | "c"] => Ok(Command::Run),
+            ["run" | "r", "until", "cycle", n] => Ok(Command::RunUntilCycle(parse_number(n)?)),
+            ["print" | "p", register] => match register.to_lowercase().as_str() {
+                "x" => Ok(Command::Print(Register::X)),
+                "cycle" => Ok(Command::Print(Register::Cycle)),
+                "pc" => Ok(Command::Print(Register::Pc)),
+                "crt" => Ok(Command::Print(Register::Crt)),
+                _ => Err(format!("unknown register `{register}`")),
+            },
+            ["break" | "b", "when", "x" | "X", "==", v] => Ok(Command::BreakWhen(parse_number(v)?)),
+            ["break" | "b", "clear"] => Ok(Command::ClearBreakpoints),
+            ["help" | "h"] => Ok(Command::Help),
+            ["quit" | "q"] => Ok(Command::Quit),
+            _ => Err(format!("unknown command `{}`", input.trim())),
+        }
+    }
+}
+
+const HELP: &str = "commands:
+  step [n]              run n cycles (default 1), tracing each of them
+  run                   run until a breakpoint triggers or the program ends
+  run until cycle N     run until cycle N is reached
+  print X|cycle|pc|crt  show a register or the screen drawn so far
+  break when X == v     stop whenever X becomes v
+  break clear           remove all breakpoints
+  quit";
+
+pub struct Debugger<'a> {
+    cpu: Cpu<'a>,
+    crt: Crt,
+    breakpoints: Vec<i32>,
+    last_x: Option<i32>,
+}
+
+impl<'a> Debugger<'a> {
+    pub fn new(program: &'a [Instruction], crt: Crt) -> Self {
+        Debugger {
+            cpu: Cpu::new(program),
+            crt,
+            breakpoints: vec![],
+            last_x: None,
+        }
+    }
+
+    fn step(&mut self) -> Option<(Tick, bool)> {
+        let tick = self.cpu.step()?;
+        self.crt.draw(tick.cycle, tick.x);
+        let hit = self.last_x != Some(tick.x) && self.breakpoints.contains(&tick.x);
+        self.last_x = Some(tick.x);
+        Some((tick, hit))
+    }
+
+    fn trace(&self, tick: &Tick) -> String {
+        let pixel = match self.crt.position(tick.cycle) {
+            Some((row, column)) => format!(
+                "pixel {row},{column:>2} {}",
+                if self.crt.is_lit(row, column) {
+                    '#'
+                } else {
+                    '.'
+                }
+            ),
+            None => String::from("off screen"),
+        };
+        format!(
+            "cycle {:>3} | X = {:>3} | {pixel} | {:>4}: {}",
+            tick.cycle, tick.x, tick.pc, tick.instruction
+        )
+    }
+
+    fn run_while(
+        &mut self,
+        output: &mut impl Write,
+        keep_going: impl Fn(&Cpu) -> bool,
+    ) -> io::Result<()> {
+        let mut last_tick = None;
+        while keep_going(&self.cpu) {
+            match self.step() {
+                Some((tick, true)) => {
+                    writeln!(output, "breakpoint: X == {}", tick.x)?;
+                    return writeln!(output, "{}", self.trace(&tick));
+                }
+                Some((tick, false)) => last_tick = Some(tick),
+                None => break,
+            }
+        }
+        if let Some(tick) = last_tick {
+            writeln!(output, "{}", self.trace(&tick))?;
+        }
+        if self.cpu.is_halted() {
+            writeln!(output, "program halted after {} cycles", self.cpu.cycle())?;
+        }
+        Ok(())
+    }
+
+    /// Executes a single command, returning `false` once the session should end.
+    pub fn execute(&mut self, command: Command, output: &mut impl Write) -> io::Result<bool> {
+        match command {
+            Command::Step(n) => {
+                for _ in 0..n {
+                    match self.step() {
+                        Some((tick, hit)) => {
+                            writeln!(output, "{}", self.trace(&tick))?;
+                            if hit {
+                                writeln!(output, "breakpoint: X == {}", tick.x)?;
+                                break;
+                            }
+                        }
+                        None => {
+                            writeln!(output, "program halted after {} cycles", self.cpu.cycle())?;
+                            break;
+                        }
+                    }
+                }
+            }
+            Command::Run => self.run_while(output, |_| true)?,
+            Command::RunUntilCycle(target) => self.run_while(output, |cpu| cpu.cycle() < target)?,
+            Command::Print(Register::X) => writeln!(output, "X = {}", self.cpu.x())?,
+            Command::Print(Register::Cycle) => writeln!(output, "cycle = {}", self.cpu.cycle())?,
+            Command::Print(Register::Pc) => writeln!(output, "pc = {}", self.cpu.pc())?,
+            Command::Print(Register::Crt) => writeln!(output, "{}", self.crt)?,
+            Command::BreakWhen(v) => {
+                self.breakpoints.push(v);
+                writeln!(
+                    output,
+                    "breakpoint {} set: X == {v}",
+                    self.breakpoints.len()
+                )?;
+            }
+            Command::ClearBreakpoints => self.breakpoints.clear(),
+            Command::Help => writeln!(output, "{HELP}")?,
+            Command::Quit => return Ok(false),
+        }
+        Ok(true)
+    }
+}
+
+/// Interactive step debugger reading commands line by line from `input`.
+pub fn run_debugger(
+    program: &[Instruction],
+    crt: Crt,
+    input: impl BufRead,
+    mut output: impl Write,
+) -> io::Result<()> {
+    let mut debugger = Debugger::new(program, crt);
+    let mut lines = input.lines();
+    loop {
+        write!(output, "(day10) ")?;
+        output.flush()?;
+        let Some(line) = lines.next() else {
+            return Ok(());
+        };
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match line.parse() {
+            Ok(command) => {
+                if !debugger.execute(command, &mut output)? {
+                    return Ok(());
+                }
+            }
+            Err(message) => writeln!(output, "{message}, type `help` for usage")?,
         }
     }
 }
@@ -31,96 +488,319 @@ pub struct Registry<'a> {
     value: i32,
     signal_strength: i32,
     desired_cycles: &'a [usize],
-    current_crt: String,
-    crt_output: Vec<String>,
-    desired_cycle_index: usize,
+    crt: Crt,
 }
 
-impl Registry<'_> {
-    pub fn execute_instruction(&mut self, instruction: &Instruction) {
-        match instruction {
-            Instruction::Noop => {
-                self.cycle_count += 1;
-                self.update_signal_strength();
-            }
-            Instruction::Addx(v) => {
-                self.cycle_count += 1;
-                self.update_signal_strength();
-                self.cycle_count += 1;
-                self.update_signal_strength();
-                self.value += v;
-            }
-        };
+impl<'a> Registry<'a> {
+    pub fn new(desired_cycles: &'a [usize], crt: Crt) -> Self {
+        Registry {
+            cycle_count: 0,
+            value: 1,
+            signal_strength: 0,
+            desired_cycles,
+            crt,
+        }
     }
 
-    pub fn update_signal_strength(&mut self) {
-        if self.desired_cycle_index >= self.desired_cycles.len() {
-            return;
-        }
+    pub fn signal_strength(&self) -> i32 {
+        self.signal_strength
+    }
+
+    pub fn crt(&self) -> &Crt {
+        &self.crt
+    }
 
-        if ((self.cycle_count % 40) as i32 - 1 - self.value).abs() <= 1 {
-            self.current_crt.push_str("#");
-        } else {
-            self.current_crt.push_str(".")
+    pub fn execute_instruction(&mut self, instruction: &Instruction) {
+        for _ in 0..instruction.cycles() {
+            self.cycle_count += 1;
+            self.crt.draw(self.cycle_count, self.value);
+            self.update_signal_strength();
+        }
+        if let Instruction::Addx(v) = instruction {
+            self.value += v;
         }
+    }
 
-        if self.desired_cycles[self.desired_cycle_index] <= self.cycle_count {
-            self.signal_strength +=
-                (self.desired_cycles[self.desired_cycle_index] as i32) * self.value;
-            self.desired_cycle_index += 1;
-            self.crt_output.push(self.current_crt.clone());
-            self.current_crt.clear()
+    pub fn update_signal_strength(&mut self) {
+        if self.desired_cycles.contains(&self.cycle_count) {
+            self.signal_strength += self.cycle_count as i32 * self.value;
         }
     }
 }
 
 #[aoc_generator(day10)]
 pub fn parse(input: &str) -> Vec<Instruction> {
-    input
-        .trim()
-        .lines()
-        .map(|line| line.into())
-        .collect::<Vec<Instruction>>()
+    assemble(input).unwrap_or_else(|errors| panic!("invalid program: {}", errors[0]))
 }
 
 #[aoc(day10, part1)]
-pub fn solve_part1(instructions: &Vec<Instruction>) -> i32 {
-    let mut registry = Registry {
-        cycle_count: 0,
-        value: 1,
-        signal_strength: 0,
-        current_crt: String::from(""),
-        crt_output: vec![],
-        desired_cycles: &[20, 60, 100, 140, 180, 220],
-        desired_cycle_index: 0,
-    };
+pub fn solve_part1(instructions: &[Instruction]) -> i32 {
+    let mut registry = Registry::new(&[20, 60, 100, 140, 180, 220], Crt::default());
 
     for instruction in instructions {
         registry.execute_instruction(instruction);
     }
 
-    registry.signal_strength
+    registry.signal_strength()
 }
 
 #[aoc(day10, part2)]
-pub fn solve_part2(instructions: &Vec<Instruction>) -> i32 {
-    let mut registry = Registry {
-        cycle_count: 0,
-        value: 1,
-        signal_strength: 0,
-        current_crt: String::from(""),
-        crt_output: vec![],
-        desired_cycles: &[40, 80, 120, 160, 200, 240],
-        desired_cycle_index: 0,
-    };
+pub fn solve_part2(instructions: &[Instruction]) -> String {
+    let mut registry = Registry::new(&[], Crt::default());
 
     for instruction in instructions {
         registry.execute_instruction(instruction);
     }
 
-    for crt_line in registry.crt_output {
-        println!("{}", crt_line);
+    format!("\n{}", registry.crt())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::day10::*;
+
+    const INPUT: &str = "addx 15
+addx -11
+addx 6
+addx -3
+addx 5
+addx -1
+addx -8
+addx 13
+addx 4
+noop
+addx -1
+addx 5
+addx -1
+addx 5
+addx -1
+addx 5
+addx -1
+addx 5
+addx -1
+addx -35
+addx 1
+addx 24
+addx -19
+addx 1
+addx 16
+addx -11
+noop
+noop
+addx 21
+addx -15
+noop
+noop
+addx -3
+addx 9
+addx 1
+addx -3
+addx 8
+addx 1
+addx 5
+noop
+noop
+noop
+noop
+noop
+addx -36
+noop
+addx 1
+addx 7
+noop
+noop
+noop
+addx 2
+addx 6
+noop
+noop
+noop
+noop
+noop
+addx 1
+noop
+noop
+addx 7
+addx 1
+noop
+addx -13
+addx 13
+addx 7
+noop
+addx 1
+addx -33
+noop
+noop
+noop
+addx 2
+noop
+noop
+noop
+addx 8
+noop
+addx -1
+addx 2
+addx 1
+noop
+addx 17
+addx -9
+addx 1
+addx 1
+addx -3
+addx 11
+noop
+noop
+addx 1
+noop
+addx 1
+noop
+noop
+addx -13
+addx -19
+addx 1
+addx 3
+addx 26
+addx -30
+addx 12
+addx -1
+addx 3
+addx 1
+noop
+noop
+noop
+addx -9
+addx 18
+addx 1
+addx 2
+noop
+noop
+addx 9
+noop
+noop
+noop
+addx -1
+addx 2
+addx -37
+addx 1
+addx 3
+noop
+addx 15
+addx -21
+addx 22
+addx -6
+addx 1
+noop
+addx 2
+addx 1
+noop
+addx -10
+noop
+noop
+addx 20
+addx 1
+addx 2
+addx 2
+addx -6
+addx -11
+noop
+noop
+noop";
+
+    #[test]
+    fn test_p1() {
+        assert_eq!(solve_part1(&parse(INPUT)), 13140);
+    }
+
+    #[test]
+    fn test_p2() {
+        assert_eq!(
+            solve_part2(&parse(INPUT)),
+            "
+##..##..##..##..##..##..##..##..##..##..
+###...###...###...###...###...###...###.
+####....####....####....####....####....
+#####.....#####.....#####.....#####.....
+######......######......######......####
+#######.......#######.......#######....."
+        );
+    }
+
+    #[test]
+    fn test_custom_display() {
+        let mut registry = Registry::new(&[], Crt::new(4, 3).with_sprite_width(1));
+        for instruction in parse("noop\naddx 1\nnoop\naddx 1\naddx 5\nnoop\nnoop\nnoop") {
+            registry.execute_instruction(&instruction);
+        }
+        assert_eq!(registry.crt().to_string(), ".#..\n...#\n....");
+
+        let mut registry = Registry::new(&[], Crt::new(5, 2).with_sprite_width(4));
+        for instruction in parse("noop\nnoop\nnoop\nnoop\nnoop\naddx 2\nnoop\nnoop\nnoop") {
+            registry.execute_instruction(&instruction);
+        }
+        assert_eq!(registry.crt().to_string(), "####.\n#####");
+    }
+
+    #[test]
+    fn test_assemble_errors() {
+        const INPUT: &str = "noop
+addx
+addx 3
+
+mul 2
+addx 4 5
+addx four";
+
+        assert_eq!(
+            assemble(INPUT),
+            Err(vec![
+                AssemblyError {
+                    line: 2,
+                    kind: AssemblyErrorKind::MissingOperand("addx")
+                },
+                AssemblyError {
+                    line: 5,
+                    kind: AssemblyErrorKind::UnknownOpcode("mul".to_string())
+                },
+                AssemblyError {
+                    line: 6,
+                    kind: AssemblyErrorKind::UnexpectedOperand("5".to_string())
+                },
+                AssemblyError {
+                    line: 7,
+                    kind: AssemblyErrorKind::InvalidOperand("four".to_string())
+                },
+            ])
+        );
     }
 
-    registry.signal_strength
+    #[test]
+    fn test_disassemble() {
+        let program = assemble("noop\naddx 3\naddx -5").unwrap();
+        assert_eq!(
+            disassemble(&program),
+            "cycle 1         noop       ; X = 1
+cycle 2-3       addx 3     ; X = 1 -> 4
+cycle 4-5       addx -5    ; X = 4 -> -1
+"
+        );
+    }
+
+    #[test]
+    fn test_debugger_session() {
+        let program = assemble("noop\naddx 3\naddx -5").unwrap();
+        let commands = "break when X == 4\nrun\nprint X\nrun until cycle 5\nstep\nquit\n";
+        let mut output = vec![];
+        run_debugger(&program, Crt::default(), commands.as_bytes(), &mut output).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "(day10) breakpoint 1 set: X == 4
+(day10) breakpoint: X == 4
+cycle   4 | X =   4 | pixel 0, 3 # |    2: addx -5
+(day10) X = 4
+(day10) cycle   5 | X =   4 | pixel 0, 4 # |    2: addx -5
+program halted after 5 cycles
+(day10) program halted after 5 cycles
+(day10) "
+        );
+    }
 }
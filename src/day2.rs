@@ -1,141 +1,276 @@
 use aoc_runner_derive::{aoc, aoc_generator};
 
-#[derive(Clone, Copy, Debug)]
-pub enum Move {
-    Rock,
-    Paper,
-    Scissors,
-}
+pub mod analysis;
 
-impl From<char> for Move {
-    fn from(c: char) -> Move {
-        match c {
-            'A' | 'X' => Move::Rock,
-            'B' | 'Y' => Move::Paper,
-            'C' | 'Z' => Move::Scissors,
-            _ => unreachable!(),
-        }
-    }
-}
+/// Move of a game, by its position in the cycle of the game's moves.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Move(pub usize);
 
 impl Move {
-    pub fn losing_move(&self) -> Move {
-        match self {
-            Move::Rock => Move::Scissors,
-            Move::Paper => Move::Rock,
-            Move::Scissors => Move::Paper,
-        }
-    }
-
-    pub fn winning_move(&self) -> Move {
-        match self {
-            Move::Rock => Move::Paper,
-            Move::Paper => Move::Scissors,
-            Move::Scissors => Move::Rock,
-        }
-    }
-    pub fn score(&self) -> u8 {
-        match self {
-            Move::Rock => 1,
-            Move::Paper => 2,
-            Move::Scissors => 3,
-        }
-    }
+    pub const ROCK: Move = Move(0);
+    pub const PAPER: Move = Move(1);
+    pub const SCISSORS: Move = Move(2);
 }
 
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum Outcome {
     Loss,
     Draw,
     Win,
 }
 
-impl From<char> for Outcome {
-    fn from(c: char) -> Outcome {
-        match c {
-            'X' => Outcome::Loss,
-            'Y' => Outcome::Draw,
-            'Z' => Outcome::Win,
-            _ => unreachable!(),
+impl Outcome {
+    pub const ALL: [Outcome; 3] = [Outcome::Loss, Outcome::Draw, Outcome::Win];
+}
+
+/// Game where moves form a cycle, every move beating the `(n - 1) / 2` moves before it and
+/// losing to the ones after it. The number of moves `n` is odd, so that every move beats as
+/// many moves as it loses to.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Game {
+    names: Vec<String>,
+    move_scores: Vec<u32>,
+    // Indexed as `Outcome::ALL`
+    outcome_scores: [u32; 3],
+}
+
+impl Game {
+    /// Game with the given moves in cycle order, scoring 1 for the first move, 2 for the
+    /// next one and so on, plus 0 for a loss, 3 for a draw and 6 for a win.
+    pub fn new<'a>(names: impl IntoIterator<Item = &'a str>) -> Self {
+        let names = names.into_iter().map(String::from).collect::<Vec<String>>();
+        assert!(
+            names.len() % 2 == 1,
+            "a cyclic game needs an odd number of moves"
+        );
+        Self {
+            move_scores: (1..=names.len() as u32).collect(),
+            names,
+            outcome_scores: [0, 3, 6],
         }
     }
-}
 
-impl Outcome {
-    pub fn score(&self) -> u8 {
-        match self {
-            Outcome::Loss => 0,
-            Outcome::Draw => 3,
-            Outcome::Win => 6,
+    pub fn rock_paper_scissors() -> Self {
+        Self::new(["Rock", "Paper", "Scissors"])
+    }
+
+    /// Rock-Paper-Scissors-Lizard-Spock, with moves ordered so that each of them beats the
+    /// two moves before it.
+    pub fn rock_paper_scissors_lizard_spock() -> Self {
+        Self::new(["Rock", "Spock", "Paper", "Lizard", "Scissors"])
+    }
+
+    /// Panics if there is not one score per move.
+    pub fn with_move_scores(mut self, scores: Vec<u32>) -> Self {
+        assert_eq!(
+            scores.len(),
+            self.names.len(),
+            "expected one score per move"
+        );
+        self.move_scores = scores;
+        self
+    }
+
+    pub fn with_outcome_scores(mut self, loss: u32, draw: u32, win: u32) -> Self {
+        self.outcome_scores = [loss, draw, win];
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    pub fn moves(&self) -> impl Iterator<Item = Move> {
+        (0..self.len()).map(Move)
+    }
+
+    pub fn name(&self, play: Move) -> &str {
+        &self.names[play.0]
+    }
+
+    pub fn find(&self, name: &str) -> Option<Move> {
+        self.names
+            .iter()
+            .position(|other| other.eq_ignore_ascii_case(name))
+            .map(Move)
+    }
+
+    /// Outcome of `mine` against `theirs`.
+    pub fn outcome(&self, mine: Move, theirs: Move) -> Outcome {
+        match (mine.0 + self.len() - theirs.0) % self.len() {
+            0 => Outcome::Draw,
+            distance if distance <= self.len() / 2 => Outcome::Win,
+            _ => Outcome::Loss,
         }
     }
+
+    /// Move getting the outcome against `theirs`, the closest one in the cycle when several
+    /// moves would do.
+    pub fn move_for(&self, theirs: Move, outcome: Outcome) -> Move {
+        let offset = match outcome {
+            Outcome::Loss => self.len() - 1,
+            Outcome::Draw => 0,
+            Outcome::Win => 1,
+        };
+        Move((theirs.0 + offset) % self.len())
+    }
+
+    pub fn outcome_score(&self, outcome: Outcome) -> u32 {
+        self.outcome_scores[outcome as usize]
+    }
+
+    pub fn move_score(&self, play: Move) -> u32 {
+        self.move_scores[play.0]
+    }
+
+    /// Score of a round for the player of `mine`.
+    pub fn score(&self, mine: Move, theirs: Move) -> u32 {
+        self.move_score(mine) + self.outcome_score(self.outcome(mine, theirs))
+    }
 }
 
-#[derive(Debug)]
+/// Line of the strategy guide, both columns being kept as indices from `A` and `X`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Round {
-    p1_move: Move,
-    p2_move: Move,
-    desired_outcome: Outcome,
+    opponent: Move,
+    response: usize,
 }
 
 impl Round {
     fn from_line(line: &str) -> Self {
         let chars: Vec<char> = line.split(' ').map(|e| e.chars().next().unwrap()).collect();
         Self {
-            p1_move: Move::from(chars[0]),
-            p2_move: Move::from(chars[1].clone()),
-            desired_outcome: Outcome::from(chars[1]),
+            opponent: Move(chars[0] as usize - 'A' as usize),
+            response: chars[1] as usize - 'X' as usize,
         }
     }
 
-    fn outcome_from_plays(&self) -> Outcome {
-        match (self.p1_move, self.p2_move) {
-            (Move::Rock, Move::Scissors) => Outcome::Loss,
-            (Move::Scissors, Move::Rock) => Outcome::Win,
-            (p1_move, p2_move) if p2_move.score() > p1_move.score() => Outcome::Win,
-            (p1_move, p2_move) if p2_move.score() == p1_move.score() => Outcome::Draw,
-            _ => Outcome::Loss,
-        }
+    pub fn opponent(&self) -> Move {
+        self.opponent
     }
 
-    fn my_score_from_plays(&self) -> u8 {
-        self.outcome_from_plays().score() + self.p2_move.score()
+    pub fn response(&self) -> usize {
+        self.response
     }
 
-    fn move_to_do(&self) -> Move {
-        match self.desired_outcome {
-            Outcome::Loss => self.p1_move.losing_move(),
-            Outcome::Win => self.p1_move.winning_move(),
-            Outcome::Draw => self.p1_move.clone(),
-        }
+    /// Score when the second column is the move to play.
+    fn score_from_plays(&self, game: &Game) -> u32 {
+        game.score(Move(self.response), self.opponent)
     }
 
-    fn score_from_desired_outcome(&self) -> u8 {
-        self.desired_outcome.score() + self.move_to_do().score()
+    /// Score when the second column is the outcome to get.
+    fn score_from_desired_outcome(&self, game: &Game) -> u32 {
+        let outcome = Outcome::ALL[self.response];
+        game.score(game.move_for(self.opponent, outcome), self.opponent)
     }
 }
 
 #[aoc_generator(day2)]
 pub fn input_generator(input: &str) -> Vec<Round> {
-    input
-        .trim()
-        .lines()
-        .map(|line| Round::from_line(line))
-        .collect()
+    input.trim().lines().map(Round::from_line).collect()
 }
 
 #[aoc(day2, part1)]
-pub fn solve_part1(rounds: &Vec<Round>) -> u32 {
+pub fn solve_part1(rounds: &[Round]) -> u32 {
+    let game = Game::rock_paper_scissors();
     rounds
         .iter()
-        .map(|round| u32::from(round.my_score_from_plays()))
+        .map(|round| round.score_from_plays(&game))
         .sum()
 }
 
 #[aoc(day2, part2)]
-pub fn solve_part2(rounds: &Vec<Round>) -> u32 {
+pub fn solve_part2(rounds: &[Round]) -> u32 {
+    let game = Game::rock_paper_scissors();
     rounds
         .iter()
-        .map(|round| u32::from(round.score_from_desired_outcome()))
+        .map(|round| round.score_from_desired_outcome(&game))
         .sum()
 }
+
+#[cfg(test)]
+mod test {
+    use crate::day2::*;
+
+    const INPUT: &str = "A Y
+B X
+C Z";
+
+    #[test]
+    fn test_p1() {
+        assert_eq!(solve_part1(&input_generator(INPUT)), 15);
+    }
+
+    #[test]
+    fn test_p2() {
+        assert_eq!(solve_part2(&input_generator(INPUT)), 12);
+    }
+
+    #[test]
+    fn test_rock_paper_scissors() {
+        let game = Game::rock_paper_scissors();
+        assert_eq!(game.outcome(Move::ROCK, Move::SCISSORS), Outcome::Win);
+        assert_eq!(game.outcome(Move::SCISSORS, Move::ROCK), Outcome::Loss);
+        assert_eq!(game.outcome(Move::PAPER, Move::PAPER), Outcome::Draw);
+        assert_eq!(game.move_for(Move::ROCK, Outcome::Loss), Move::SCISSORS);
+        assert_eq!(game.move_for(Move::SCISSORS, Outcome::Win), Move::ROCK);
+
+        let custom = game
+            .with_move_scores(vec![10, 20, 30])
+            .with_outcome_scores(1, 2, 4);
+        assert_eq!(custom.score(Move::ROCK, Move::SCISSORS), 14);
+        assert_eq!(custom.score(Move::ROCK, Move::PAPER), 11);
+    }
+
+    #[test]
+    fn test_lizard_spock() {
+        let game = Game::rock_paper_scissors_lizard_spock();
+        let play = |name| game.find(name).unwrap();
+        let beats = [
+            ("scissors", "paper"),
+            ("paper", "rock"),
+            ("rock", "lizard"),
+            ("lizard", "spock"),
+            ("spock", "scissors"),
+            ("scissors", "lizard"),
+            ("lizard", "paper"),
+            ("paper", "spock"),
+            ("spock", "rock"),
+            ("rock", "scissors"),
+        ];
+        for (winner, loser) in beats {
+            assert_eq!(game.outcome(play(winner), play(loser)), Outcome::Win);
+            assert_eq!(game.outcome(play(loser), play(winner)), Outcome::Loss);
+        }
+        for theirs in game.moves() {
+            for outcome in Outcome::ALL {
+                assert_eq!(
+                    game.outcome(game.move_for(theirs, outcome), theirs),
+                    outcome
+                );
+            }
+        }
+        assert_eq!(
+            game.name(game.move_for(play("rock"), Outcome::Win)),
+            "Spock"
+        );
+    }
+
+    #[test]
+    fn test_larger_cycles() {
+        let game = Game::new(["a", "b", "c", "d", "e", "f", "g"]);
+        for mine in game.moves() {
+            let wins = game
+                .moves()
+                .filter(|&theirs| game.outcome(mine, theirs) == Outcome::Win)
+                .count();
+            assert_eq!(wins, 3);
+        }
+        assert_eq!(game.outcome(Move(0), Move(4)), Outcome::Win);
+        assert_eq!(game.outcome(Move(0), Move(3)), Outcome::Loss);
+    }
+}
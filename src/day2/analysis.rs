@@ -0,0 +1,159 @@
+use itertools::Itertools;
+
+use crate::day2::{Game, Move, Round};
+
+/// Reading of the second column of the guide as moves, for `X`, `Y` and `Z` in this order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Interpretation(pub [Move; 3]);
+
+impl Interpretation {
+    /// Every way of assigning distinct moves of the game to `X`, `Y` and `Z`, the 6
+    /// permutations of the moves for Rock-Paper-Scissors.
+    pub fn all(game: &Game) -> Vec<Self> {
+        game.moves()
+            .permutations(3)
+            .map(|moves| Self([moves[0], moves[1], moves[2]]))
+            .collect()
+    }
+
+    pub fn play(&self, round: &Round) -> Move {
+        self.0[round.response()]
+    }
+
+    pub fn describe(&self, game: &Game) -> String {
+        ['X', 'Y', 'Z']
+            .iter()
+            .zip(self.0)
+            .map(|(column, play)| format!("{column}={}", game.name(play)))
+            .join(", ")
+    }
+}
+
+/// Probabilities of playing each move of a game, whatever the opponent plays.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MixedStrategy {
+    probabilities: Vec<f64>,
+}
+
+impl MixedStrategy {
+    pub fn uniform(game: &Game) -> Self {
+        Self {
+            probabilities: vec![1.0 / game.len() as f64; game.len()],
+        }
+    }
+
+    /// Plays each move as often as it was counted. Panics if nothing was counted.
+    pub fn from_counts(counts: &[usize]) -> Self {
+        let total = counts.iter().sum::<usize>();
+        assert!(total > 0, "a strategy needs at least one counted move");
+        Self {
+            probabilities: counts
+                .iter()
+                .map(|&count| count as f64 / total as f64)
+                .collect(),
+        }
+    }
+
+    /// Plays each move as often as the opponent column of the guide does.
+    pub fn mirroring(game: &Game, rounds: &[Round]) -> Self {
+        let mut counts = vec![0; game.len()];
+        rounds
+            .iter()
+            .for_each(|round| counts[round.opponent().0] += 1);
+        Self::from_counts(&counts)
+    }
+
+    pub fn probability(&self, play: Move) -> f64 {
+        self.probabilities[play.0]
+    }
+}
+
+/// Score when following the guide as read with `interpretation`.
+pub fn interpretation_score(game: &Game, rounds: &[Round], interpretation: &Interpretation) -> u32 {
+    rounds
+        .iter()
+        .map(|round| game.score(interpretation.play(round), round.opponent()))
+        .sum()
+}
+
+/// Score of every interpretation of the guide, the highest first.
+pub fn interpretation_scores(game: &Game, rounds: &[Round]) -> Vec<(Interpretation, u32)> {
+    Interpretation::all(game)
+        .into_iter()
+        .map(|interpretation| {
+            let score = interpretation_score(game, rounds, &interpretation);
+            (interpretation, score)
+        })
+        .sorted_by_key(|&(_, score)| std::cmp::Reverse(score))
+        .collect()
+}
+
+/// Highest score against the opponent column, playing the best move at every round.
+pub fn best_score(game: &Game, rounds: &[Round]) -> u32 {
+    rounds
+        .iter()
+        .map(|round| {
+            game.moves()
+                .map(|play| game.score(play, round.opponent()))
+                .max()
+                .unwrap_or(0)
+        })
+        .sum()
+}
+
+/// Expected score against the opponent column when drawing every move from `strategy`.
+pub fn expected_score(game: &Game, rounds: &[Round], strategy: &MixedStrategy) -> f64 {
+    rounds
+        .iter()
+        .flat_map(|round| {
+            game.moves()
+                .map(|play| strategy.probability(play) * game.score(play, round.opponent()) as f64)
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::day2::analysis::*;
+    use crate::day2::input_generator;
+
+    const INPUT: &str = "A Y
+B X
+C Z";
+
+    #[test]
+    fn test_interpretations() {
+        let (game, rounds) = (Game::rock_paper_scissors(), input_generator(INPUT));
+        let scores = interpretation_scores(&game, &rounds);
+        assert_eq!(scores.len(), 6);
+        assert_eq!(
+            scores.iter().map(|&(_, score)| score).collect::<Vec<u32>>(),
+            [24, 15, 15, 15, 15, 6]
+        );
+        assert_eq!(scores[0].0.describe(&game), "X=Scissors, Y=Paper, Z=Rock");
+        let literal = Interpretation([Move::ROCK, Move::PAPER, Move::SCISSORS]);
+        assert_eq!(interpretation_score(&game, &rounds, &literal), 15);
+        assert_eq!(
+            Interpretation::all(&Game::rock_paper_scissors_lizard_spock()).len(),
+            60
+        );
+    }
+
+    #[test]
+    fn test_strategies() {
+        let (game, rounds) = (Game::rock_paper_scissors(), input_generator(INPUT));
+        assert_eq!(best_score(&game, &rounds), 24);
+        let uniform = expected_score(&game, &rounds, &MixedStrategy::uniform(&game));
+        assert!((uniform - 15.0).abs() < 1e-9);
+        let mirroring = MixedStrategy::mirroring(&game, &rounds);
+        assert!((expected_score(&game, &rounds, &mirroring) - 15.0).abs() < 1e-9);
+        let rock = MixedStrategy::from_counts(&[2, 0, 0]);
+        assert!((expected_score(&game, &rounds, &rock) - 12.0).abs() < 1e-9);
+
+        let always_win = game
+            .clone()
+            .with_outcome_scores(0, 0, 1)
+            .with_move_scores(vec![0; 3]);
+        assert_eq!(best_score(&always_win, &rounds), 3);
+    }
+}
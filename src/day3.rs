@@ -1,10 +1,10 @@
-use std::{collections::HashMap, vec};
+use std::{fmt, ops::BitAnd, str::FromStr};
 
 use aoc_runner_derive::aoc;
 
 #[aoc(day3, part1)]
 pub fn solve_part1(txt: &str) -> u32 {
-    txt.trim().lines().map(count_line_priority).sum()
+    compartments_priorities(txt).unwrap_or_else(|err| panic!("invalid rucksacks: {err}"))
 }
 
 const UPPERCASE_A: u32 = 'A' as u32;
@@ -12,60 +12,417 @@ const LOWERCASE_A: u32 = 'a' as u32;
 const UPPERCASE_OFFSET: u32 = UPPERCASE_A - 27;
 const LOWERCASE_OFFSET: u32 = LOWERCASE_A - 1;
 
-pub fn get_priority(c: char) -> u32 {
-    println!("finding priority {}", c);
-    match c as u32 {
-        v if v < ('Z' as u32 + 1) => c as u32 - UPPERCASE_OFFSET,
-        v if v < ('z' as u32 + 1) => c as u32 - LOWERCASE_OFFSET,
-        _ => unreachable!(),
+pub fn get_priority(c: char) -> Option<u32> {
+    match c {
+        'A'..='Z' => Some(c as u32 - UPPERCASE_OFFSET),
+        'a'..='z' => Some(c as u32 - LOWERCASE_OFFSET),
+        _ => None,
     }
 }
 
-pub fn count_line_priority(line: &str) -> u32 {
-    let mut seen_characters: HashMap<char, u32> = HashMap::new();
-    let last_first_part_char = (line.len() / 2) - 1;
-    for (i, c) in line.chars().enumerate() {
-        match (i, c) {
-            (i, _) if i <= last_first_part_char => *seen_characters.entry(c).or_insert(0) += 1,
-            (_, c) if seen_characters.contains_key(&c) => return get_priority(c),
-            (_, _) => continue,
-        };
+pub fn get_item(priority: u32) -> Option<char> {
+    match priority {
+        1..=26 => char::from_u32(priority + LOWERCASE_OFFSET),
+        27..=52 => char::from_u32(priority + UPPERCASE_OFFSET),
+        _ => None,
     }
-    unreachable!()
 }
 
-pub fn count_characters(line: &str) -> HashMap<char, u32> {
-    let mut seen_characters: HashMap<char, u32> = HashMap::new();
-    line.chars()
-        .for_each(|c| *seen_characters.entry(c).or_insert(0) += 1);
-    seen_characters
+/// Set of item types, bit `p` standing for the item of priority `p`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Items(u64);
+
+impl Items {
+    /// Every item type, from priority 1 to 52.
+    pub const ALL: Items = Items(((1 << 53) - 1) & !1);
+
+    pub fn parse(items: &str) -> Result<Self, RucksackErrorKind> {
+        items.chars().try_fold(Self::default(), |set, c| {
+            let priority = get_priority(c).ok_or(RucksackErrorKind::InvalidItem(c))?;
+            Ok(Self(set.0 | 1 << priority))
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.count_ones() as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn priorities(&self) -> impl Iterator<Item = u32> {
+        let bits = self.0;
+        (1..=52).filter(move |priority| bits & 1 << priority != 0)
+    }
+
+    pub fn items(&self) -> impl Iterator<Item = char> {
+        self.priorities().filter_map(get_item)
+    }
+
+    /// Priority of the only item of the set.
+    pub fn single(&self) -> Result<u32, RucksackErrorKind> {
+        match self.len() {
+            1 => Ok(self.0.trailing_zeros()),
+            0 => Err(RucksackErrorKind::NoCommonItem),
+            _ => Err(RucksackErrorKind::SeveralCommonItems(
+                self.items().collect(),
+            )),
+        }
+    }
+}
+
+impl BitAnd for Items {
+    type Output = Self;
+
+    fn bitand(self, other: Self) -> Self {
+        Self(self.0 & other.0)
+    }
 }
 
-pub fn find_dupe(lines: &Vec<&str>) -> char {
-    let mut value_count: HashMap<char, u32> = HashMap::new();
-    for (i, line) in lines.iter().enumerate() {
-        for c in line.chars() {
-            let char_count = value_count.entry(c).or_insert(0);
-            match char_count {
-                2 if i == 2 => return c,
-                char_count if char_count == &(i as u32) => *char_count += 1,
-                _ => continue,
-            };
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RucksackErrorKind {
+    InvalidItem(char),
+    UnevenLength { length: usize, compartments: usize },
+    NoCommonItem,
+    SeveralCommonItems(String),
+    IncompleteGroup { expected: usize, found: usize },
+}
+
+/// Error about a rucksack, or about the group starting with it, at a line from 1.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RucksackError {
+    pub line: usize,
+    pub kind: RucksackErrorKind,
+}
+
+impl fmt::Display for RucksackError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: ", self.line)?;
+        match &self.kind {
+            RucksackErrorKind::InvalidItem(c) => write!(f, "`{c}` is not an item"),
+            RucksackErrorKind::UnevenLength {
+                length,
+                compartments,
+            } => write!(
+                f,
+                "{length} items cannot be split in {compartments} equal compartments"
+            ),
+            RucksackErrorKind::NoCommonItem => write!(f, "no item is common to all"),
+            RucksackErrorKind::SeveralCommonItems(items) => {
+                write!(f, "several items are common to all: {items}")
+            }
+            RucksackErrorKind::IncompleteGroup { expected, found } => {
+                write!(f, "group has {found} rucksacks instead of {expected}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for RucksackError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rucksack {
+    // Only holds letters, so that compartments can be split at any byte
+    contents: String,
+}
+
+impl Rucksack {
+    pub fn items(&self) -> Items {
+        Items::parse(&self.contents).unwrap()
+    }
+
+    /// Items of each of `count` equal compartments. Panics if `count` is 0.
+    pub fn compartments(&self, count: usize) -> Result<Vec<Items>, RucksackErrorKind> {
+        assert!(count > 0, "a rucksack has at least one compartment");
+        let length = self.contents.len();
+        if !length.is_multiple_of(count) {
+            return Err(RucksackErrorKind::UnevenLength {
+                length,
+                compartments: count,
+            });
+        }
+        // An empty rucksack still has its compartments, with nothing in them
+        if length == 0 {
+            return Ok(vec![Items::default(); count]);
         }
+        Ok(self
+            .contents
+            .as_bytes()
+            .chunks(length / count)
+            .map(|chunk| Items::parse(std::str::from_utf8(chunk).unwrap()).unwrap())
+            .collect())
+    }
+
+    /// Items found in every one of `count` equal compartments.
+    pub fn common_items(&self, count: usize) -> Result<Items, RucksackErrorKind> {
+        let compartments = self.compartments(count)?;
+        Ok(compartments.into_iter().fold(Items::ALL, BitAnd::bitand))
+    }
+}
+
+impl FromStr for Rucksack {
+    type Err = RucksackErrorKind;
+
+    fn from_str(contents: &str) -> Result<Self, Self::Err> {
+        Items::parse(contents)?;
+        Ok(Self {
+            contents: contents.to_string(),
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Inventory {
+    rucksacks: Vec<Rucksack>,
+}
+
+impl Inventory {
+    pub fn rucksacks(&self) -> &[Rucksack] {
+        &self.rucksacks
     }
-    unreachable!()
+
+    /// Items shared by every compartment of every rucksack of a group, for each group of
+    /// `group_size` rucksacks, the rucksacks being split in `compartments` equal parts.
+    /// Panics if either count is 0.
+    pub fn common_items(
+        &self,
+        group_size: usize,
+        compartments: usize,
+    ) -> Result<Vec<Items>, RucksackError> {
+        self.rucksacks
+            .chunks(group_size)
+            .enumerate()
+            .map(|(index, group)| {
+                let line = index * group_size + 1;
+                if group.len() != group_size {
+                    let kind = RucksackErrorKind::IncompleteGroup {
+                        expected: group_size,
+                        found: group.len(),
+                    };
+                    return Err(RucksackError { line, kind });
+                }
+                group
+                    .iter()
+                    .enumerate()
+                    .try_fold(Items::ALL, |common, (offset, rucksack)| {
+                        let items =
+                            rucksack
+                                .common_items(compartments)
+                                .map_err(|kind| RucksackError {
+                                    line: line + offset,
+                                    kind,
+                                })?;
+                        Ok(common & items)
+                    })
+            })
+            .collect()
+    }
+
+    /// Sum of the priorities of the items shared in each group, which must be alone.
+    pub fn priorities(&self, group_size: usize, compartments: usize) -> Result<u32, RucksackError> {
+        self.common_items(group_size, compartments)?
+            .iter()
+            .enumerate()
+            .map(|(index, items)| {
+                items.single().map_err(|kind| RucksackError {
+                    line: index * group_size + 1,
+                    kind,
+                })
+            })
+            .sum()
+    }
+}
+
+impl FromStr for Inventory {
+    type Err = RucksackError;
+
+    fn from_str(txt: &str) -> Result<Self, Self::Err> {
+        let rucksacks = txt
+            .trim()
+            .lines()
+            .enumerate()
+            .map(|(index, line)| {
+                line.parse().map_err(|kind| RucksackError {
+                    line: index + 1,
+                    kind,
+                })
+            })
+            .collect::<Result<Vec<Rucksack>, RucksackError>>()?;
+        Ok(Self { rucksacks })
+    }
+}
+
+pub fn compartments_priorities(txt: &str) -> Result<u32, RucksackError> {
+    txt.parse::<Inventory>()?.priorities(1, 2)
+}
+
+/// Sum of the group priorities, `group_size` rucksacks at a time. Panics if it is 0.
+pub fn group_priorities(txt: &str, group_size: usize) -> Result<u32, RucksackError> {
+    txt.parse::<Inventory>()?.priorities(group_size, 1)
 }
 
 #[aoc(day3, part2)]
 pub fn solve_part2(txt: &str) -> u32 {
-    let mut group: Vec<&str> = vec![];
-    let mut total_priority: u32 = 0;
-    for (i, line) in txt.trim().lines().enumerate() {
-        group.push(line);
-        if i % 3 == 2 {
-            total_priority += get_priority(find_dupe(&group));
-            group.clear();
-        }
+    group_priorities(txt, 3).unwrap_or_else(|err| panic!("invalid rucksacks: {err}"))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::day3::*;
+
+    const INPUT: &str = "vJrwpWtwJgWrhcsFMMfFFhFp
+jqHRNqRjqzjGDLGLrsFMfFZSrLrFZsSL
+PmmdzqPrVvPwwTWBwg
+wMqvLMZHhHMvwLHjbvcjnnSBnvTQFn
+ttgJtRGJQctTZtZT
+CrZsJsPPZsGzwwsLwLmpwMDw";
+
+    #[test]
+    fn test_p1() {
+        assert_eq!(solve_part1(INPUT), 157);
+    }
+
+    #[test]
+    fn test_p2() {
+        assert_eq!(solve_part2(INPUT), 70);
+    }
+
+    #[test]
+    fn test_items() {
+        assert_eq!(get_priority('a'), Some(1));
+        assert_eq!(get_priority('Z'), Some(52));
+        assert_eq!(get_priority('1'), None);
+        assert_eq!(get_item(27), Some('A'));
+        let items = Items::parse("abZa").unwrap();
+        assert_eq!(items.len(), 3);
+        assert_eq!(items.items().collect::<String>(), "abZ");
+        assert_eq!(group_priorities("ab\nbc\ndb", 3), Ok(2));
+        assert_eq!(
+            group_priorities("aB", 1),
+            Err(RucksackError {
+                line: 1,
+                kind: RucksackErrorKind::SeveralCommonItems("aB".into())
+            })
+        );
+        assert_eq!(group_priorities("ab\nbc\ncd\nde", 2), Ok(2 + 4));
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(
+            compartments_priorities("abca\nabc"),
+            Err(RucksackError {
+                line: 2,
+                kind: RucksackErrorKind::UnevenLength {
+                    length: 3,
+                    compartments: 2
+                }
+            })
+        );
+        assert_eq!(
+            compartments_priorities("abcd"),
+            Err(RucksackError {
+                line: 1,
+                kind: RucksackErrorKind::NoCommonItem
+            })
+        );
+        assert_eq!(
+            compartments_priorities("aa\n\nbb"),
+            Err(RucksackError {
+                line: 2,
+                kind: RucksackErrorKind::NoCommonItem
+            })
+        );
+        assert_eq!(
+            group_priorities("ab\n\nb", 3),
+            Err(RucksackError {
+                line: 1,
+                kind: RucksackErrorKind::NoCommonItem
+            })
+        );
+        assert_eq!(
+            compartments_priorities("a-a-"),
+            Err(RucksackError {
+                line: 1,
+                kind: RucksackErrorKind::InvalidItem('-')
+            })
+        );
+        assert_eq!(
+            group_priorities("ab\nbc\nbd\nab", 3),
+            Err(RucksackError {
+                line: 4,
+                kind: RucksackErrorKind::IncompleteGroup {
+                    expected: 3,
+                    found: 1
+                }
+            })
+        );
+        assert_eq!(
+            group_priorities("ab\nab\ncd", 3),
+            Err(RucksackError {
+                line: 1,
+                kind: RucksackErrorKind::NoCommonItem
+            })
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "at least one compartment")]
+    fn test_no_compartment() {
+        let _ = "".parse::<Rucksack>().unwrap().common_items(0);
+    }
+
+    #[test]
+    fn test_common_items() {
+        let inventory: Inventory = INPUT.parse().unwrap();
+        let items = |group_size, compartments| {
+            inventory
+                .common_items(group_size, compartments)
+                .unwrap()
+                .iter()
+                .map(|items| items.items().collect())
+                .collect::<Vec<String>>()
+        };
+        assert_eq!(items(1, 2), ["p", "L", "P", "v", "t", "s"]);
+        assert_eq!(items(3, 1), ["r", "Z"]);
+        assert_eq!(items(2, 1), ["frsFM", "qvwBT", "GJZ"]);
+        assert_eq!(items(6, 1), [""]);
+        assert_eq!(inventory.rucksacks()[2].compartments(3).unwrap().len(), 3);
+
+        let rucksack: Rucksack = "abcabdabe".parse().unwrap();
+        assert_eq!(
+            rucksack
+                .common_items(3)
+                .unwrap()
+                .items()
+                .collect::<String>(),
+            "ab"
+        );
+        assert_eq!(rucksack.common_items(9).unwrap(), Items::default());
+        assert_eq!(rucksack.common_items(1).unwrap(), rucksack.items());
+        assert_eq!(
+            rucksack.common_items(2),
+            Err(RucksackErrorKind::UnevenLength {
+                length: 9,
+                compartments: 2
+            })
+        );
+
+        let inventory: Inventory = "abab\nabcabd\nabc".parse().unwrap();
+        assert_eq!(
+            inventory.common_items(3, 2),
+            Err(RucksackError {
+                line: 3,
+                kind: RucksackErrorKind::UnevenLength {
+                    length: 3,
+                    compartments: 2
+                }
+            })
+        );
+        assert_eq!(
+            inventory.common_items(3, 1).unwrap(),
+            [Items::parse("ab").unwrap()]
+        );
     }
-    total_priority
 }
@@ -1,29 +1,17 @@
-use std::cmp;
+use std::{cmp::Reverse, collections::BinaryHeap};
 
 use aoc_runner_derive::{aoc, aoc_generator};
 
-pub struct Section(u32, u32);
+use crate::interval::Interval;
 
-impl Section {
-    pub fn intersection(&self, s2: &Section) -> Option<Section> {
-        match (self, s2) {
-            (s1, s2) if s1.1 < s2.0 || s2.1 < s1.0 => None,
-            _ => Some(Self(cmp::max(self.0, s2.0), cmp::min(self.1, s2.1))),
-        }
-    }
-    pub fn size(&self) -> u32 {
-        self.1 - self.0
-    }
-}
+pub type Section = Interval<u32>;
 
-impl From<&str> for Section {
-    fn from(input: &str) -> Self {
-        let mut split = input.split('-');
-        Self(
-            split.next().unwrap().parse().unwrap(),
-            split.next().unwrap().parse().unwrap(),
-        )
-    }
+fn parse_section(input: &str) -> Section {
+    let mut split = input.split('-');
+    Section::new(
+        split.next().unwrap().parse().unwrap(),
+        split.next().unwrap().parse().unwrap(),
+    )
 }
 
 pub struct Pair(Section, Section);
@@ -32,18 +20,13 @@ impl Pair {
     pub fn inter(&self) -> Option<Section> {
         self.0.intersection(&self.1)
     }
+
     pub fn has_full_inter(&self) -> bool {
-        match self.inter() {
-            Some(s) => s.size() == cmp::min(self.0.size(), self.1.size()),
-            None => false,
-        }
+        self.0.contains_interval(&self.1) || self.1.contains_interval(&self.0)
     }
 
     pub fn has_inter(&self) -> bool {
-        match self.inter() {
-            Some(_) => true,
-            None => false,
-        }
+        self.0.overlaps(&self.1)
     }
 }
 
@@ -51,23 +34,181 @@ impl From<&str> for Pair {
     fn from(input: &str) -> Self {
         let mut split = input.split(',');
         Self(
-            Section::from(split.next().unwrap()),
-            Section::from(split.next().unwrap()),
+            parse_section(split.next().unwrap()),
+            parse_section(split.next().unwrap()),
         )
     }
 }
 
+/// Elf of the input, by the index of its pair and its side in the pair, both from 0.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Elf {
+    pub pair: usize,
+    pub side: usize,
+}
+
+fn assignments(pairs: &[Pair]) -> impl Iterator<Item = (Elf, Section)> + '_ {
+    pairs
+        .iter()
+        .enumerate()
+        .flat_map(|(pair, Pair(first, second))| {
+            [(0, first), (1, second)].map(|(side, &section)| (Elf { pair, side }, section))
+        })
+}
+
+/// Runs of sections assigned to the same number of elves, in order, sections assigned to
+/// nobody being left out. Sweeps over the bounds of the assignments rather than sections.
+pub fn coverage_profile(pairs: &[Pair]) -> Vec<(Section, usize)> {
+    // Assignments start at their first section and stop right after their last one
+    let mut bounds = assignments(pairs)
+        .flat_map(|(_, section)| [(section.start() as u64, 1), (section.end() as u64 + 1, -1)])
+        .collect::<Vec<(u64, isize)>>();
+    bounds.sort_unstable();
+
+    let mut profile: Vec<(Section, usize)> = vec![];
+    let mut elves = 0;
+    for (index, &(position, change)) in bounds.iter().enumerate() {
+        elves += change;
+        let next = bounds.get(index + 1).map(|&(next, _)| next);
+        match next {
+            Some(next) if next > position && elves > 0 => {
+                let (start, end) = (position as u32, (next - 1) as u32);
+                // Elves leaving where as many others arrive do not end the run
+                match profile.last_mut() {
+                    Some((section, count))
+                        if *count == elves as usize && section.end() + 1 == start =>
+                    {
+                        *section = Section::new(section.start(), end);
+                    }
+                    _ => profile.push((Section::new(start, end), elves as usize)),
+                }
+            }
+            _ => {}
+        }
+    }
+    profile
+}
+
+/// Number of sections assigned to at least one elf.
+pub fn total_coverage(pairs: &[Pair]) -> u32 {
+    coverage_profile(pairs)
+        .iter()
+        .map(|(section, _)| section.len())
+        .sum()
+}
+
+/// First run of sections assigned to the most elves, with their number.
+pub fn busiest_sections(pairs: &[Pair]) -> Option<(Section, usize)> {
+    coverage_profile(pairs)
+        .into_iter()
+        .rev()
+        .max_by_key(|&(_, elves)| elves)
+}
+
+/// Every two elves, from the same pair or not, with overlapping assignments. Assignments are
+/// swept by start, keeping the ones not over yet, so that the work follows the number of
+/// overlaps found rather than the number of elves squared.
+pub fn overlapping_elves(pairs: &[Pair]) -> Vec<(Elf, Elf)> {
+    let mut elves = assignments(pairs).collect::<Vec<(Elf, Section)>>();
+    elves.sort_unstable_by_key(|&(elf, section)| (section.start(), elf));
+
+    let mut ongoing: BinaryHeap<Reverse<(u32, Elf)>> = BinaryHeap::new();
+    let mut overlaps = vec![];
+    for (elf, section) in elves {
+        while ongoing
+            .peek()
+            .is_some_and(|Reverse((end, _))| *end < section.start())
+        {
+            ongoing.pop();
+        }
+        overlaps.extend(
+            ongoing
+                .iter()
+                .map(|&Reverse((_, other))| (other.min(elf), other.max(elf))),
+        );
+        ongoing.push(Reverse((section.end(), elf)));
+    }
+    overlaps.sort_unstable();
+    overlaps
+}
+
 #[aoc_generator(day4)]
 pub fn input_generator(input: &str) -> Vec<Pair> {
     input.trim().lines().map(|line| line.into()).collect()
 }
 
 #[aoc(day4, part1)]
-pub fn solve_part1(pairs: &Vec<Pair>) -> usize {
+pub fn solve_part1(pairs: &[Pair]) -> usize {
     pairs.iter().filter(|pair| pair.has_full_inter()).count()
 }
 
 #[aoc(day4, part2)]
-pub fn solve_part2(pairs: &Vec<Pair>) -> usize {
+pub fn solve_part2(pairs: &[Pair]) -> usize {
     pairs.iter().filter(|pair| pair.has_inter()).count()
 }
+
+#[cfg(test)]
+mod test {
+    use crate::day4::*;
+
+    const INPUT: &str = "2-4,6-8
+2-3,4-5
+5-7,7-9
+2-8,3-7
+6-6,4-6
+2-6,4-8";
+
+    #[test]
+    fn test_p1() {
+        assert_eq!(solve_part1(&input_generator(INPUT)), 2);
+    }
+
+    #[test]
+    fn test_p2() {
+        assert_eq!(solve_part2(&input_generator(INPUT)), 4);
+    }
+
+    #[test]
+    fn test_inter() {
+        let pairs = input_generator(INPUT);
+        assert_eq!(pairs[2].inter(), Some(Section::single(7)));
+        assert_eq!(pairs[3].inter().map(|section| section.len()), Some(5));
+        assert_eq!(pairs[0].inter(), None);
+    }
+
+    #[test]
+    fn test_sweeps() {
+        let pairs = input_generator(INPUT);
+        assert_eq!(total_coverage(&pairs), 8);
+        assert_eq!(busiest_sections(&pairs), Some((Section::single(6), 8)));
+        assert_eq!(
+            coverage_profile(&pairs)
+                .iter()
+                .map(|&(section, elves)| format!("{section}:{elves}"))
+                .collect::<Vec<String>>(),
+            ["2-2:4", "3-3:5", "4-5:7", "6-6:8", "7-7:6", "8-8:4", "9-9:1"]
+        );
+
+        let elves = assignments(&pairs).collect::<Vec<(Elf, Section)>>();
+        let mut expected = vec![];
+        for (index, &(elf, section)) in elves.iter().enumerate() {
+            for &(other, other_section) in &elves[index + 1..] {
+                if section.overlaps(&other_section) {
+                    expected.push((elf, other));
+                }
+            }
+        }
+        assert_eq!(overlapping_elves(&pairs), expected);
+        assert!(expected.contains(&(Elf { pair: 2, side: 0 }, Elf { pair: 2, side: 1 })));
+        assert!(!expected.contains(&(Elf { pair: 0, side: 0 }, Elf { pair: 0, side: 1 })));
+    }
+
+    #[test]
+    fn test_disjoint_sweeps() {
+        let pairs = input_generator("1-2,5-5\n9-9,3-3");
+        assert_eq!(total_coverage(&pairs), 5);
+        assert_eq!(busiest_sections(&pairs), Some((Section::new(1, 3), 1)));
+        assert!(overlapping_elves(&pairs).is_empty());
+        assert_eq!(busiest_sections(&[]), None);
+    }
+}
@@ -1,8 +1,12 @@
-use std::cmp;
+use std::{
+    fmt,
+    io::{self, Write},
+    str::FromStr,
+};
 
 use aoc_runner_derive::aoc;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Move {
     quantity: usize,
     from: usize,
@@ -10,73 +14,972 @@ pub struct Move {
 }
 
 impl Move {
-    fn exec(&self, stacks: &mut Vec<Vec<char>>) {
-        for _ in 0..cmp::min(self.quantity, stacks[self.from - 1].len()) {
-            let e = stacks[self.from - 1].pop().unwrap();
-            stacks[self.to - 1].push(e);
+    /// Move of `quantity` crates between stacks numbered from 1, as in the puzzle.
+    pub fn new(quantity: usize, from: usize, to: usize) -> Self {
+        Self { quantity, from, to }
+    }
+}
+
+impl FromStr for Move {
+    type Err = ParseErrorKind;
+
+    /// Reads a `move <quantity> from <stack> to <stack>` line.
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let words = input.split_whitespace().collect::<Vec<&str>>();
+        match words[..] {
+            ["move", quantity, "from", from, "to", to] => {
+                let number = |word: &str| word.parse().map_err(|_| ParseErrorKind::MalformedMove);
+                Ok(Self::new(number(quantity)?, number(from)?, number(to)?))
+            }
+            _ => Err(ParseErrorKind::MalformedMove),
+        }
+    }
+}
+
+impl fmt::Display for Move {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "move {} from {} to {}",
+            self.quantity, self.from, self.to
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MoveError {
+    NoSuchStack {
+        stack: usize,
+        stacks: usize,
+    },
+    NotEnoughCrates {
+        stack: usize,
+        requested: usize,
+        available: usize,
+    },
+}
+
+impl fmt::Display for MoveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MoveError::NoSuchStack { stack, stacks } => {
+                write!(f, "there is no stack {stack}, only {stacks} stacks")
+            }
+            MoveError::NotEnoughCrates {
+                stack,
+                requested,
+                available,
+            } => write!(
+                f,
+                "cannot take {requested} crates from stack {stack} holding {available}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for MoveError {}
+
+/// Label of a crate, the letters between its brackets in the drawing.
+pub type Crate = String;
+
+/// Stacks of crates, the top of a stack being the end of its vector.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Stacks {
+    stacks: Vec<Vec<Crate>>,
+}
+
+impl Stacks {
+    pub fn new(stacks: Vec<Vec<Crate>>) -> Self {
+        Self { stacks }
+    }
+
+    pub fn len(&self) -> usize {
+        self.stacks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.stacks.is_empty()
+    }
+
+    /// Crates of a stack numbered from 1, bottom first.
+    pub fn get(&self, stack: usize) -> Option<&[Crate]> {
+        let index = stack.checked_sub(1)?;
+        self.stacks.get(index).map(Vec::as_slice)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &[Crate]> {
+        self.stacks.iter().map(Vec::as_slice)
+    }
+
+    /// Crates on top of every stack, empty stacks being skipped.
+    pub fn tops(&self) -> String {
+        self.stacks
+            .iter()
+            .filter_map(|stack| stack.last())
+            .map(String::as_str)
+            .collect()
+    }
+
+    fn check(&self, mv: &Move) -> Result<(), MoveError> {
+        check_move(mv, self.len(), |stack| self.stacks[stack - 1].len())
+    }
+
+    /// Both stacks of a move, which have been checked to be distinct and to exist.
+    fn pair_mut(&mut self, from: usize, to: usize) -> (&mut Vec<Crate>, &mut Vec<Crate>) {
+        let (from, to) = (from - 1, to - 1);
+        if from < to {
+            let (left, right) = self.stacks.split_at_mut(to);
+            (&mut left[from], &mut right[0])
+        } else {
+            let (left, right) = self.stacks.split_at_mut(from);
+            (&mut right[0], &mut left[to])
+        }
+    }
+
+    /// Moves `quantity` crates from the top of `from` to the top of `to`, `from` holding at
+    /// least that many crates, reversing their order if `reverses` is set.
+    fn transfer(&mut self, from: usize, to: usize, quantity: usize, reverses: bool) {
+        // Crates put back on the stack they were taken from do not move
+        if from == to {
+            return;
+        }
+        let (from, to) = self.pair_mut(from, to);
+        let crates = from.drain(from.len() - quantity..);
+        if reverses {
+            to.extend(crates.rev());
+        } else {
+            to.extend(crates);
         }
     }
 
-    fn exec_p2(&self, stacks: &mut Vec<Vec<char>>) {
-        let starting_range = cmp::max(stacks[self.from - 1].len() - self.quantity, 0);
-        let mut retrieved_containers = stacks[self.from - 1].split_off(starting_range);
-        stacks[self.to - 1].append(&mut retrieved_containers);
+    /// Applies a move with the given crane, leaving the stacks untouched if it is invalid.
+    pub fn apply(&mut self, crane: CraneModel, mv: &Move) -> Result<(), MoveError> {
+        self.check(mv)?;
+        self.transfer(mv.from, mv.to, mv.quantity, crane.reverses());
+        Ok(())
+    }
+
+    /// Cancels a move applied with the given crane. Moving the crates back with the same
+    /// crane is enough, whether it keeps or reverses their order.
+    fn revert(&mut self, crane: CraneModel, mv: &Move) {
+        self.transfer(mv.to, mv.from, mv.quantity, crane.reverses());
+    }
+}
+
+/// Drawing of the stacks as in the puzzle, padded to the same width on every line. Cells
+/// are as wide as the longest label so that columns stay aligned.
+impl fmt::Display for Stacks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let height = self.stacks.iter().map(Vec::len).max().unwrap_or(0);
+        let labels = self.stacks.iter().flatten().map(|c| c.chars().count());
+        let width = labels.max().unwrap_or(1) + 2;
+        for level in (0..height).rev() {
+            let cells = self
+                .stacks
+                .iter()
+                .map(|stack| match stack.get(level) {
+                    Some(c) => format!("{:width$}", format!("[{c}]")),
+                    None => " ".repeat(width),
+                })
+                .collect::<Vec<String>>();
+            writeln!(f, "{}", cells.join(" "))?;
+        }
+        let footer = (1..=self.stacks.len())
+            .map(|number| format!("{number:^width$}"))
+            .collect::<Vec<String>>();
+        write!(f, "{}", footer.join(" "))
     }
 }
 
-impl From<&str> for Move {
-    fn from(input: &str) -> Self {
-        let mut split_input = input.split(' ');
+/// Checks that both stacks of a move exist and that there are enough crates to take,
+/// `height` giving the number of crates of an existing stack.
+fn check_move(mv: &Move, stacks: usize, height: impl Fn(usize) -> usize) -> Result<(), MoveError> {
+    for stack in [mv.from, mv.to] {
+        if stack == 0 || stack > stacks {
+            return Err(MoveError::NoSuchStack { stack, stacks });
+        }
+    }
+    let available = height(mv.from);
+    if mv.quantity > available {
+        return Err(MoveError::NotEnoughCrates {
+            stack: mv.from,
+            requested: mv.quantity,
+            available,
+        });
+    }
+    Ok(())
+}
+
+/// Crane models, which only differ in the order crates land in on the other stack.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CraneModel {
+    /// Moves crates one at a time, so they land in reverse order.
+    Mover9000,
+    /// Moves several crates at once, keeping their order.
+    Mover9001,
+}
+
+impl CraneModel {
+    /// Whether the crates taken from a stack land in reverse order.
+    pub fn reverses(self) -> bool {
+        matches!(self, CraneModel::Mover9000)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct RopeNode {
+    priority: u64,
+    left: Option<usize>,
+    right: Option<usize>,
+    size: usize,
+    // The crates of the subtree are to be read in reverse, children not being swapped yet
+    reversed: bool,
+}
+
+/// Stacks kept as treaps ordered by position, bottom first, for long crane programs: a move
+/// splits the crates off the top of a stack, flags them as reversed for a crane working one
+/// crate at a time, and merges them onto the other stack, whatever the number of crates.
+/// Nodes are shared by all the stacks, a node standing for the crate with the same index.
+#[derive(Debug, Clone)]
+pub struct RopeStacks {
+    labels: Vec<Crate>,
+    nodes: Vec<RopeNode>,
+    roots: Vec<Option<usize>>,
+}
+
+impl RopeStacks {
+    pub fn new(stacks: &Stacks) -> Self {
+        let mut rope = Self {
+            labels: vec![],
+            nodes: vec![],
+            roots: vec![],
+        };
+        // Priorities only need to look random for the treaps to stay balanced
+        let mut seed = 0x9e37_79b9_7f4a_7c15u64;
+        for stack in stacks.iter() {
+            let mut root = None;
+            for label in stack {
+                seed ^= seed << 13;
+                seed ^= seed >> 7;
+                seed ^= seed << 17;
+                rope.labels.push(label.clone());
+                rope.nodes.push(RopeNode {
+                    priority: seed,
+                    left: None,
+                    right: None,
+                    size: 1,
+                    reversed: false,
+                });
+                root = rope.merge(root, Some(rope.nodes.len() - 1));
+            }
+            rope.roots.push(root);
+        }
+        rope
+    }
+
+    pub fn len(&self) -> usize {
+        self.roots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.roots.is_empty()
+    }
+
+    /// Number of crates of a stack numbered from 1.
+    pub fn height(&self, stack: usize) -> Option<usize> {
+        let root = *self.roots.get(stack.checked_sub(1)?)?;
+        Some(self.size(root))
+    }
+
+    fn size(&self, node: Option<usize>) -> usize {
+        node.map_or(0, |node| self.nodes[node].size)
+    }
+
+    fn update(&mut self, node: usize) {
+        let RopeNode { left, right, .. } = self.nodes[node];
+        self.nodes[node].size = 1 + self.size(left) + self.size(right);
+    }
+
+    fn push_down(&mut self, node: usize) {
+        let RopeNode {
+            left,
+            right,
+            reversed,
+            ..
+        } = self.nodes[node];
+        if reversed {
+            for child in [left, right].into_iter().flatten() {
+                self.nodes[child].reversed ^= true;
+            }
+            self.nodes[node].left = right;
+            self.nodes[node].right = left;
+            self.nodes[node].reversed = false;
+        }
+    }
+
+    /// Splits a treap into its first `count` crates and the others.
+    fn split(&mut self, node: Option<usize>, count: usize) -> (Option<usize>, Option<usize>) {
+        let Some(node) = node else {
+            return (None, None);
+        };
+        self.push_down(node);
+        let RopeNode { left, right, .. } = self.nodes[node];
+        let left_size = self.size(left);
+        if count <= left_size {
+            let (first, rest) = self.split(left, count);
+            self.nodes[node].left = rest;
+            self.update(node);
+            (first, Some(node))
+        } else {
+            let (first, rest) = self.split(right, count - left_size - 1);
+            self.nodes[node].right = first;
+            self.update(node);
+            (Some(node), rest)
+        }
+    }
+
+    fn merge(&mut self, first: Option<usize>, second: Option<usize>) -> Option<usize> {
+        let (first, second) = match (first, second) {
+            (None, node) | (node, None) => return node,
+            (Some(first), Some(second)) => (first, second),
+        };
+        if self.nodes[first].priority > self.nodes[second].priority {
+            self.push_down(first);
+            let right = self.merge(self.nodes[first].right, Some(second));
+            self.nodes[first].right = right;
+            self.update(first);
+            Some(first)
+        } else {
+            self.push_down(second);
+            let left = self.merge(Some(first), self.nodes[second].left);
+            self.nodes[second].left = left;
+            self.update(second);
+            Some(second)
+        }
+    }
+
+    /// Applies a move with the given crane, leaving the stacks untouched if it is invalid.
+    pub fn apply(&mut self, crane: CraneModel, mv: &Move) -> Result<(), MoveError> {
+        check_move(mv, self.len(), |stack| self.size(self.roots[stack - 1]))?;
+        if mv.from != mv.to {
+            let (from, to) = (mv.from - 1, mv.to - 1);
+            let kept = self.size(self.roots[from]) - mv.quantity;
+            let (rest, moved) = self.split(self.roots[from], kept);
+            if let (Some(moved), true) = (moved, crane.reverses()) {
+                self.nodes[moved].reversed ^= true;
+            }
+            self.roots[from] = rest;
+            self.roots[to] = self.merge(self.roots[to], moved);
+        }
+        Ok(())
+    }
+
+    /// Crates on top of every stack, empty stacks being skipped.
+    pub fn tops(&self) -> String {
+        let mut tops = String::new();
+        for &root in &self.roots {
+            let Some(mut node) = root else {
+                continue;
+            };
+            // Follows the last crate down, children being swapped under an odd number of flags
+            let mut reversed = false;
+            loop {
+                reversed ^= self.nodes[node].reversed;
+                let RopeNode { left, right, .. } = self.nodes[node];
+                match if reversed { left } else { right } {
+                    Some(next) => node = next,
+                    None => break,
+                }
+            }
+            tops.push_str(&self.labels[node]);
+        }
+        tops
+    }
+
+    fn collect(&self, node: Option<usize>, reversed: bool, crates: &mut Vec<Crate>) {
+        let Some(node) = node else {
+            return;
+        };
+        let reversed = reversed ^ self.nodes[node].reversed;
+        let RopeNode { left, right, .. } = self.nodes[node];
+        let (first, last) = if reversed {
+            (right, left)
+        } else {
+            (left, right)
+        };
+        self.collect(first, reversed, crates);
+        crates.push(self.labels[node].clone());
+        self.collect(last, reversed, crates);
+    }
+
+    pub fn to_stacks(&self) -> Stacks {
+        let stacks = self
+            .roots
+            .iter()
+            .map(|&root| {
+                let mut crates = Vec::with_capacity(self.size(root));
+                self.collect(root, false, &mut crates);
+                crates
+            })
+            .collect();
+        Stacks::new(stacks)
+    }
+}
+
+/// Runs moves with a crane, logging them so that they can be undone.
+pub struct Simulator {
+    crane: CraneModel,
+    stacks: Stacks,
+    log: Vec<Move>,
+}
+
+impl Simulator {
+    pub fn new(crane: CraneModel, stacks: Stacks) -> Self {
         Self {
-            quantity: split_input.nth(1).unwrap().parse().unwrap(),
-            from: split_input.nth(1).unwrap().parse().unwrap(),
-            to: split_input.nth(1).unwrap().parse().unwrap(),
+            crane,
+            stacks,
+            log: vec![],
+        }
+    }
+
+    pub fn stacks(&self) -> &Stacks {
+        &self.stacks
+    }
+
+    pub fn into_stacks(self) -> Stacks {
+        self.stacks
+    }
+
+    /// Moves applied so far, oldest first.
+    pub fn history(&self) -> &[Move] {
+        &self.log
+    }
+
+    pub fn apply(&mut self, mv: &Move) -> Result<(), MoveError> {
+        self.stacks.apply(self.crane, mv)?;
+        self.log.push(*mv);
+        Ok(())
+    }
+
+    /// Applies moves until one of them is invalid, returning its index with the error.
+    pub fn run<'a>(
+        &mut self,
+        moves: impl IntoIterator<Item = &'a Move>,
+    ) -> Result<(), (usize, MoveError)> {
+        moves
+            .into_iter()
+            .enumerate()
+            .try_for_each(|(index, mv)| self.apply(mv).map_err(|err| (index, err)))
+    }
+
+    /// Applies moves like `run`, writing the drawing of the stacks before the first move and
+    /// after every one of them. An invalid move stops the run with an `InvalidInput` error.
+    pub fn run_traced<'a>(
+        &mut self,
+        moves: impl IntoIterator<Item = &'a Move>,
+        out: &mut impl Write,
+    ) -> io::Result<()> {
+        writeln!(out, "{}", self.stacks)?;
+        for mv in moves {
+            self.apply(mv).map_err(|err| {
+                io::Error::new(io::ErrorKind::InvalidInput, format!("`{mv}`: {err}"))
+            })?;
+            writeln!(out, "\n{mv}\n\n{}", self.stacks)?;
+        }
+        Ok(())
+    }
+
+    /// Undoes the last move, returning it.
+    pub fn undo(&mut self) -> Option<Move> {
+        let mv = self.log.pop()?;
+        self.stacks.revert(self.crane, &mv);
+        Some(mv)
+    }
+
+    /// Undoes moves until only `len` of them remain applied.
+    pub fn rewind(&mut self, len: usize) {
+        while self.log.len() > len {
+            self.undo();
         }
     }
 }
 
-// #[aoc_generator(day5)]
-pub fn parse_input_generator(input: &str) -> (Vec<Vec<char>>, Vec<Move>) {
-    let mut split = input.split("\n\n");
-    let stack_lines = split.next().unwrap().lines().rev();
-    let stack_chars = stack_lines
-        .skip(1)
-        .map(|line| line.chars().skip(1).step_by(4));
-    let mut stacks = vec![];
-    stack_chars.for_each(|line| push_line(&mut stacks, line));
-
-    let moves = split
-        .next()
-        .unwrap()
-        .lines()
-        .map(|line| line.into())
-        .collect();
-
-    (stacks, moves)
-}
-
-pub fn push_line(stacks: &mut Vec<Vec<char>>, line: impl Iterator<Item = char>) {
-    line.enumerate()
-        .filter(|(_, c)| c != &' ')
-        .for_each(|(i, c)| {
-            let stack = stacks.get(i);
-            match stack {
-                Some(_) => stacks[i].push(c),
-                None => stacks.push(vec![c]),
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseErrorKind {
+    MissingFooter,
+    InvalidStackNumber { expected: usize, found: String },
+    MalformedCrate { column: usize },
+    UnalignedCrate { column: usize },
+    FloatingCrate { stack: usize },
+    MalformedMove,
+    NoSuchStack { stack: usize },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub line: usize,
+    pub kind: ParseErrorKind,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: ", self.line)?;
+        match &self.kind {
+            ParseErrorKind::MissingFooter => write!(f, "the drawing has no stack numbers"),
+            ParseErrorKind::InvalidStackNumber { expected, found } => {
+                write!(f, "expected stack number {expected}, found `{found}`")
+            }
+            ParseErrorKind::MalformedCrate { column } => {
+                write!(f, "column {column}: expected a crate such as `[A]`")
+            }
+            ParseErrorKind::UnalignedCrate { column } => {
+                write!(
+                    f,
+                    "column {column}: crate is not above a single stack number"
+                )
+            }
+            ParseErrorKind::FloatingCrate { stack } => {
+                write!(f, "crate of stack {stack} is floating above an empty space")
+            }
+            ParseErrorKind::MalformedMove => {
+                write!(f, "expected `move <quantity> from <stack> to <stack>`")
+            }
+            ParseErrorKind::NoSuchStack { stack } => write!(f, "there is no stack {stack}"),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Character columns of the words of a line, first and last ones included, with the words.
+fn words(line: &str) -> Vec<(usize, usize, String)> {
+    let mut words = vec![];
+    let mut current: Option<(usize, String)> = None;
+    for (column, c) in line.chars().chain([' ']).enumerate() {
+        match (c.is_whitespace(), current.as_mut()) {
+            (false, Some((_, word))) => word.push(c),
+            (false, None) => current = Some((column, c.to_string())),
+            (true, _) => {
+                if let Some((start, word)) = current.take() {
+                    words.push((start, column - 1, word));
+                }
+            }
+        }
+    }
+    words
+}
+
+/// Crates of a drawing line, with the columns of their brackets.
+fn crates(line: &str) -> Result<Vec<(usize, usize, Crate)>, ParseErrorKind> {
+    let chars = line.chars().collect::<Vec<char>>();
+    let mut crates = vec![];
+    let mut column = 0;
+    while column < chars.len() {
+        if chars[column].is_whitespace() {
+            column += 1;
+            continue;
+        }
+        let malformed = ParseErrorKind::MalformedCrate { column: column + 1 };
+        if chars[column] != '[' {
+            return Err(malformed);
+        }
+        let length = chars[column..]
+            .iter()
+            .position(|&c| c == ']')
+            .ok_or(malformed.clone())?;
+        let label = chars[column + 1..column + length]
+            .iter()
+            .collect::<String>();
+        if label.is_empty() || label.contains(|c: char| c == '[' || c.is_whitespace()) {
+            return Err(malformed);
+        }
+        crates.push((column, column + length, label));
+        column += length + 1;
+    }
+    Ok(crates)
+}
+
+/// Reads the stacks drawing and the moves, the columns of the stacks being given by the
+/// numbers below the drawing rather than by a fixed layout.
+pub fn parse_program(input: &str) -> Result<(Stacks, Vec<Move>), ParseError> {
+    let lines = input.lines().collect::<Vec<&str>>();
+    let separator = lines
+        .iter()
+        .position(|line| line.trim().is_empty())
+        .unwrap_or(lines.len());
+    let error = |line: usize, kind| ParseError {
+        line: line + 1,
+        kind,
+    };
+
+    let footer_line = separator
+        .checked_sub(1)
+        .ok_or(error(0, ParseErrorKind::MissingFooter))?;
+    let footer = words(lines[footer_line]);
+    for (index, (_, _, word)) in footer.iter().enumerate() {
+        if word.starts_with('[') {
+            return Err(error(footer_line, ParseErrorKind::MissingFooter));
+        }
+        if word.parse() != Ok(index + 1) {
+            let kind = ParseErrorKind::InvalidStackNumber {
+                expected: index + 1,
+                found: word.clone(),
             };
-        });
+            return Err(error(footer_line, kind));
+        }
+    }
+
+    let mut stacks = vec![vec![]; footer.len()];
+    for line_index in (0..footer_line).rev() {
+        let mut filled = vec![false; footer.len()];
+        for (start, end, label) in
+            crates(lines[line_index]).map_err(|kind| error(line_index, kind))?
+        {
+            let mut below = footer
+                .iter()
+                .enumerate()
+                .filter(|(_, &(first, last, _))| start <= last && first <= end)
+                .map(|(stack, _)| stack);
+            let unaligned = ParseErrorKind::UnalignedCrate { column: start + 1 };
+            let stack = match (below.next(), below.next()) {
+                (Some(stack), None) if !filled[stack] => stack,
+                _ => return Err(error(line_index, unaligned)),
+            };
+            // Lines are read bottom up, a crate lands right above the previous one
+            if stacks[stack].len() != footer_line - 1 - line_index {
+                let kind = ParseErrorKind::FloatingCrate { stack: stack + 1 };
+                return Err(error(line_index, kind));
+            }
+            filled[stack] = true;
+            stacks[stack].push(label);
+        }
+    }
+
+    let mut moves = vec![];
+    for (line_index, line) in lines.iter().enumerate().skip(separator + 1) {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let mv: Move = line.parse().map_err(|kind| error(line_index, kind))?;
+        if let Some(stack) = [mv.from, mv.to]
+            .into_iter()
+            .find(|&stack| stack == 0 || stack > footer.len())
+        {
+            return Err(error(line_index, ParseErrorKind::NoSuchStack { stack }));
+        }
+        moves.push(mv);
+    }
+
+    Ok((Stacks::new(stacks), moves))
+}
+
+// #[aoc_generator(day5)]
+pub fn parse_input_generator(input: &str) -> (Stacks, Vec<Move>) {
+    parse_program(input).unwrap_or_else(|err| panic!("invalid crane program: {err}"))
+}
+
+fn operate(input: &str, crane: CraneModel) -> String {
+    let (stacks, moves) = parse_input_generator(input);
+    let mut simulator = Simulator::new(crane, stacks);
+    if let Err((index, err)) = simulator.run(&moves) {
+        panic!("invalid move `{}`: {err}", moves[index]);
+    }
+    simulator.stacks().tops()
 }
 
 #[aoc(day5, part1)]
 pub fn solve_part1(input: &str) -> String {
-    let (mut stacks, moves) = parse_input_generator(input);
-    moves.iter().for_each(|mv| mv.exec(&mut stacks));
-    stacks.iter().map(|stack| stack.last().unwrap()).collect()
+    operate(input, CraneModel::Mover9000)
 }
 
 #[aoc(day5, part2)]
 pub fn solve_part2(input: &str) -> String {
-    let (mut stacks, moves) = parse_input_generator(input);
-    moves.iter().for_each(|mv| mv.exec_p2(&mut stacks));
-    stacks.iter().map(|stack| stack.last().unwrap()).collect()
+    operate(input, CraneModel::Mover9001)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::day5::*;
+
+    const INPUT: &str = "    [D]    
+[N] [C]    
+[Z] [M] [P]
+ 1   2   3 
+
+move 1 from 2 to 1
+move 3 from 1 to 3
+move 2 from 2 to 1
+move 1 from 1 to 2";
+
+    #[test]
+    fn test_p1() {
+        assert_eq!(solve_part1(INPUT), "CMZ");
+    }
+
+    #[test]
+    fn test_p2() {
+        assert_eq!(solve_part2(INPUT), "MCD");
+    }
+
+    #[test]
+    fn test_drawing() {
+        let (stacks, moves) = parse_input_generator(INPUT);
+        assert_eq!(stacks.to_string(), INPUT.split("\n\n").next().unwrap());
+        assert_eq!(Stacks::default().to_string(), "");
+
+        let mut trace = vec![];
+        let mut simulator = Simulator::new(CraneModel::Mover9000, stacks);
+        simulator.run_traced(&moves[..2], &mut trace).unwrap();
+        assert_eq!(
+            String::from_utf8(trace).unwrap(),
+            "    [D]    
+[N] [C]    
+[Z] [M] [P]
+ 1   2   3 
+
+move 1 from 2 to 1
+
+[D]        
+[N] [C]    
+[Z] [M] [P]
+ 1   2   3 
+
+move 3 from 1 to 3
+
+        [Z]
+        [N]
+    [C] [D]
+    [M] [P]
+ 1   2   3 
+"
+        );
+
+        let error = simulator
+            .run_traced(&[Move::new(1, 1, 2)], &mut io::sink())
+            .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        assert_eq!(
+            error.to_string(),
+            "`move 1 from 1 to 2`: cannot take 1 crates from stack 1 holding 0"
+        );
+    }
+
+    #[test]
+    fn test_invalid_moves() {
+        let (stacks, _) = parse_input_generator(INPUT);
+        let mut simulator = Simulator::new(CraneModel::Mover9000, stacks.clone());
+        assert_eq!(
+            simulator.apply(&Move::new(1, 4, 1)),
+            Err(MoveError::NoSuchStack {
+                stack: 4,
+                stacks: 3
+            })
+        );
+        assert_eq!(
+            simulator.apply(&Move::new(1, 1, 0)),
+            Err(MoveError::NoSuchStack {
+                stack: 0,
+                stacks: 3
+            })
+        );
+        assert_eq!(
+            simulator.apply(&Move::new(2, 3, 1)),
+            Err(MoveError::NotEnoughCrates {
+                stack: 3,
+                requested: 2,
+                available: 1
+            })
+        );
+        assert_eq!(
+            simulator.run(&[Move::new(1, 3, 1), Move::new(1, 3, 2)]),
+            Err((
+                1,
+                MoveError::NotEnoughCrates {
+                    stack: 3,
+                    requested: 1,
+                    available: 0
+                }
+            ))
+        );
+        assert_eq!(simulator.history(), [Move::new(1, 3, 1)]);
+        assert_eq!(simulator.stacks().get(3), Some(&[][..]));
+        assert_eq!(simulator.stacks().tops(), "PD");
+    }
+
+    #[test]
+    fn test_undo() {
+        let (stacks, moves) = parse_input_generator(INPUT);
+        let mut simulator = Simulator::new(CraneModel::Mover9000, stacks.clone());
+        simulator.run(&moves).unwrap();
+        simulator.rewind(2);
+        assert_eq!(simulator.history(), &moves[..2]);
+        assert_eq!(simulator.stacks().get(3).unwrap().concat(), "PDNZ");
+        assert_eq!(simulator.undo(), Some(moves[1]));
+        assert_eq!(simulator.undo(), Some(moves[0]));
+        assert_eq!(simulator.undo(), None);
+        assert_eq!(simulator.stacks(), &stacks);
+
+        let mut simulator = Simulator::new(CraneModel::Mover9001, stacks.clone());
+        simulator.run(&moves).unwrap();
+        simulator.run(&[Move::new(2, 3, 3)]).unwrap();
+        simulator.rewind(0);
+        assert_eq!(simulator.into_stacks(), stacks);
+    }
+
+    #[test]
+    fn test_wide_drawing() {
+        let input = "        [B]                             [K]
+    [A] [C]                             [L]
+ 1   2   3   4   5   6   7   8   9   10  11
+
+move 1 from 11 to 1
+move 2 from 3 to 10";
+        let (stacks, moves) = parse_program(input).unwrap();
+        assert_eq!(stacks.len(), 11);
+        assert_eq!(stacks.get(1), Some(&[][..]));
+        assert_eq!(stacks.get(3).unwrap().concat(), "CB");
+        assert_eq!(stacks.get(11).unwrap().concat(), "LK");
+        assert_eq!(stacks.tops(), "ABK");
+        assert_eq!(parse_program(&stacks.to_string()).unwrap().0, stacks);
+
+        let mut simulator = Simulator::new(CraneModel::Mover9000, stacks);
+        simulator.run(&moves).unwrap();
+        assert_eq!(simulator.stacks().tops(), "KACL");
+    }
+
+    #[test]
+    fn test_long_labels() {
+        let input = "[AB]      [CDE]
+[F]  [GH] [I]
+  1    2    3
+
+move 2 from 1 to 2";
+        let (stacks, moves) = parse_program(input).unwrap();
+        assert_eq!(
+            stacks.get(1),
+            Some(&["F".to_string(), "AB".to_string()][..])
+        );
+        assert_eq!(stacks.tops(), "ABGHCDE");
+        assert_eq!(
+            stacks.to_string(),
+            "[AB]        [CDE]
+[F]   [GH]  [I]  
+  1     2     3  "
+        );
+        assert_eq!(parse_program(&stacks.to_string()).unwrap().0, stacks);
+
+        let mut simulator = Simulator::new(CraneModel::Mover9001, stacks);
+        simulator.run(&moves).unwrap();
+        assert_eq!(simulator.stacks().get(2).unwrap().concat(), "GHFAB");
+    }
+
+    #[test]
+    fn test_invalid_drawings() {
+        let kind = |input: &str| parse_program(input).map(|_| ()).unwrap_err();
+        assert_eq!(
+            kind("[A]\n\nmove 1 from 1 to 1"),
+            ParseError {
+                line: 1,
+                kind: ParseErrorKind::MissingFooter
+            }
+        );
+        assert_eq!(
+            kind("[A] [B]\n 1   3"),
+            ParseError {
+                line: 2,
+                kind: ParseErrorKind::InvalidStackNumber {
+                    expected: 2,
+                    found: "3".to_string()
+                }
+            }
+        );
+        assert_eq!(
+            kind("[A] B\n 1   2"),
+            ParseError {
+                line: 1,
+                kind: ParseErrorKind::MalformedCrate { column: 5 }
+            }
+        );
+        assert_eq!(
+            kind("[A] [B\n 1   2"),
+            ParseError {
+                line: 1,
+                kind: ParseErrorKind::MalformedCrate { column: 5 }
+            }
+        );
+        assert_eq!(
+            kind("  [A]\n 1   2"),
+            ParseError {
+                line: 1,
+                kind: ParseErrorKind::UnalignedCrate { column: 3 }
+            }
+        );
+        assert_eq!(
+            kind("[A] [B]\n    [C]\n 1   2"),
+            ParseError {
+                line: 1,
+                kind: ParseErrorKind::FloatingCrate { stack: 1 }
+            }
+        );
+        assert_eq!(
+            kind("[A]\n 1\n\nmove one from 1 to 1"),
+            ParseError {
+                line: 4,
+                kind: ParseErrorKind::MalformedMove
+            }
+        );
+        assert_eq!(
+            kind("[A]\n 1\n\nmove 1 from 1 to 1\nmove 1 from 1 to 2"),
+            ParseError {
+                line: 5,
+                kind: ParseErrorKind::NoSuchStack { stack: 2 }
+            }
+        );
+    }
+
+    #[test]
+    fn test_rope() {
+        let (stacks, moves) = parse_input_generator(INPUT);
+        let mut rope = RopeStacks::new(&stacks);
+        for mv in &moves {
+            rope.apply(CraneModel::Mover9000, mv).unwrap();
+        }
+        assert_eq!(rope.tops(), "CMZ");
+        assert_eq!(rope.height(1), Some(1));
+        assert_eq!(rope.height(4), None);
+        assert_eq!(
+            rope.apply(CraneModel::Mover9000, &Move::new(2, 2, 1)),
+            Err(MoveError::NotEnoughCrates {
+                stack: 2,
+                requested: 2,
+                available: 1
+            })
+        );
+
+        // Random moves, both models agreeing with the vector stacks
+        let mut stacks = Stacks::new(
+            (0..5)
+                .map(|stack| (0..20).map(|c| format!("{stack}{c}")).collect())
+                .collect(),
+        );
+        let mut rope = RopeStacks::new(&stacks);
+        let mut seed = 42u64;
+        for step in 0..2000 {
+            seed = seed
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            let (from, to) = ((seed >> 33) as usize % 5 + 1, (seed >> 40) as usize % 5 + 1);
+            let height = stacks.get(from).unwrap().len();
+            let mv = Move::new((seed >> 20) as usize % (height + 1), from, to);
+            if step % 2 == 0 {
+                stacks.apply(CraneModel::Mover9000, &mv).unwrap();
+                rope.apply(CraneModel::Mover9000, &mv).unwrap();
+            } else {
+                stacks.apply(CraneModel::Mover9001, &mv).unwrap();
+                rope.apply(CraneModel::Mover9001, &mv).unwrap();
+            }
+            assert_eq!(rope.tops(), stacks.tops());
+        }
+        assert_eq!(rope.to_stacks(), stacks);
+    }
 }
@@ -1,34 +1,300 @@
 use aoc_runner_derive::aoc;
-use std::collections::HashSet;
+use std::io::{self, Read};
 
-pub fn get_first_marker(line: &str, matching_chars: usize) -> usize {
-    let mut char_set = HashSet::new();
-    for i in 0..line.len() - matching_chars + 1 {
-        char_set.clear();
-        line[i..i + matching_chars].chars().for_each(|c| {
-            char_set.insert(c);
-        });
-        if char_set.len() == matching_chars {
-            return i + matching_chars;
+/// Sliding window over a stream of symbols, keeping a count of each of them so that
+/// checking the window for duplicates does not depend on its size.
+pub struct MarkerDetector {
+    window: Vec<u8>,
+    counts: [u32; 256],
+    // Symbols of the window that are the repetition of an earlier one
+    duplicates: usize,
+    read: usize,
+}
+
+impl MarkerDetector {
+    pub fn new(matching_chars: usize) -> Self {
+        assert!(matching_chars > 0, "a marker needs at least one character");
+        Self {
+            window: vec![0; matching_chars],
+            counts: [0; 256],
+            duplicates: 0,
+            read: 0,
+        }
+    }
+
+    /// Number of symbols pushed so far.
+    pub fn position(&self) -> usize {
+        self.read
+    }
+
+    /// Pushes the next symbol, returning whether the window now ends on a marker.
+    pub fn push(&mut self, symbol: u8) -> bool {
+        let size = self.window.len();
+        let slot = self.read % size;
+        if self.read >= size {
+            let leaving = self.window[slot] as usize;
+            self.counts[leaving] -= 1;
+            if self.counts[leaving] > 0 {
+                self.duplicates -= 1;
+            }
+        }
+        if self.counts[symbol as usize] > 0 {
+            self.duplicates += 1;
+        }
+        self.counts[symbol as usize] += 1;
+        self.window[slot] = symbol;
+        self.read += 1;
+        self.read >= size && self.duplicates == 0
+    }
+}
+
+/// Iterator over the positions of every marker of a stream, a position being the number
+/// of symbols read when the marker is complete. Every byte is a symbol, newlines included.
+pub struct Markers<R> {
+    reader: R,
+    buffer: Box<[u8]>,
+    filled: usize,
+    consumed: usize,
+    detector: MarkerDetector,
+}
+
+impl<R: Read> Markers<R> {
+    pub fn new(reader: R, matching_chars: usize) -> Self {
+        Self {
+            reader,
+            buffer: vec![0; 64 * 1024].into_boxed_slice(),
+            filled: 0,
+            consumed: 0,
+            detector: MarkerDetector::new(matching_chars),
+        }
+    }
+}
+
+impl<R: Read> Iterator for Markers<R> {
+    type Item = io::Result<usize>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            while self.consumed < self.filled {
+                let symbol = self.buffer[self.consumed];
+                self.consumed += 1;
+                if self.detector.push(symbol) {
+                    return Some(Ok(self.detector.position()));
+                }
+            }
+            match self.reader.read(&mut self.buffer) {
+                Ok(0) => return None,
+                Ok(read) => {
+                    self.filled = read;
+                    self.consumed = 0;
+                }
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => return Some(Err(err)),
+            }
         }
     }
-    0
+}
+
+pub fn first_marker_in(reader: impl Read, matching_chars: usize) -> io::Result<Option<usize>> {
+    Markers::new(reader, matching_chars).next().transpose()
+}
+
+pub fn get_first_marker(line: &str, matching_chars: usize) -> Option<usize> {
+    find_marker_counting(line.as_bytes(), matching_chars)
+}
+
+pub fn find_marker_counting(signal: &[u8], matching_chars: usize) -> Option<usize> {
+    let mut detector = MarkerDetector::new(matching_chars);
+    signal
+        .iter()
+        .position(|&symbol| detector.push(symbol))
+        .map(|index| index + 1)
+}
+
+/// Same as `get_first_marker`, using the bit-parallel search.
+pub fn get_first_marker_bitmask(line: &str, matching_chars: usize) -> Option<usize> {
+    find_marker_bitmask(line.as_bytes(), matching_chars)
+}
+
+/// Bit-parallel marker search. Lowercase letters are folded into a `u32` mask, XOR-ing them
+/// in as they enter the window and out as they leave it: a repeated letter cancels itself,
+/// so the window is a marker when the mask has as many bits set as the window has letters.
+/// Signals holding anything but lowercase ASCII letters go through the counting detector.
+pub fn find_marker_bitmask(signal: &[u8], matching_chars: usize) -> Option<usize> {
+    assert!(matching_chars > 0, "a marker needs at least one character");
+    if matching_chars > 26 {
+        return find_marker_counting(signal, matching_chars);
+    }
+    #[cfg(feature = "simd")]
+    return simd::find_marker(signal, matching_chars);
+    #[cfg(not(feature = "simd"))]
+    find_marker_scalar(signal, matching_chars)
+}
+
+fn letter_bit(symbol: u8) -> u32 {
+    1 << (symbol & 31)
+}
+
+fn find_marker_scalar(signal: &[u8], matching_chars: usize) -> Option<usize> {
+    // Checking the letters by chunks keeps the early exit on long signals
+    const CHUNK: usize = 4096;
+    let letters = |part: &[u8]| part.iter().all(u8::is_ascii_lowercase);
+    let (head, tail) = signal.split_at(matching_chars.min(signal.len()));
+    if !letters(head) {
+        return find_marker_counting(signal, matching_chars);
+    }
+    let mut mask = head
+        .iter()
+        .fold(0, |mask, &symbol| mask ^ letter_bit(symbol));
+    if head.len() == matching_chars && mask.count_ones() as usize == matching_chars {
+        return Some(matching_chars);
+    }
+    let chunks = tail.chunks(CHUNK).zip(signal.chunks(CHUNK));
+    for (chunk_index, (entering, leaving)) in chunks.enumerate() {
+        if !letters(entering) {
+            return find_marker_counting(signal, matching_chars);
+        }
+        let first = chunk_index * CHUNK + matching_chars;
+        for (index, (&entering, &leaving)) in (first..).zip(entering.iter().zip(leaving)) {
+            mask ^= letter_bit(entering) ^ letter_bit(leaving);
+            if mask.count_ones() as usize == matching_chars {
+                return Some(index + 1);
+            }
+        }
+    }
+    None
+}
+
+/// Checks the windows starting at `LANES` consecutive positions at once, each lane folding
+/// the letters of its own window into its mask.
+#[cfg(feature = "simd")]
+mod simd {
+    use std::simd::{
+        cmp::{SimdPartialEq, SimdPartialOrd},
+        num::SimdUint,
+        u32x16, u8x16, Simd,
+    };
+
+    const LANES: usize = 16;
+
+    pub fn find_marker(signal: &[u8], matching_chars: usize) -> Option<usize> {
+        let starts = (signal.len() + 1).saturating_sub(matching_chars);
+        let expected = u32x16::splat(matching_chars as u32);
+        let mut start = 0;
+        while start + LANES <= starts {
+            let mut masks = u32x16::splat(0);
+            for offset in 0..matching_chars {
+                let from = start + offset;
+                let symbols = u8x16::from_slice(&signal[from..from + LANES]);
+                let letters =
+                    symbols.simd_ge(Simd::splat(b'a')) & symbols.simd_le(Simd::splat(b'z'));
+                if !letters.all() {
+                    return super::find_marker_counting(signal, matching_chars);
+                }
+                masks ^= u32x16::splat(1) << (symbols.cast::<u32>() & Simd::splat(31));
+            }
+            let markers = masks.count_ones().simd_eq(expected).to_bitmask();
+            if markers != 0 {
+                return Some(start + markers.trailing_zeros() as usize + matching_chars);
+            }
+            start += LANES;
+        }
+        // Windows starting before `start` hold no marker, the remainder can be searched alone
+        super::find_marker_scalar(&signal[start..], matching_chars).map(|end| start + end)
+    }
+}
+
+pub fn get_all_markers(line: &str, matching_chars: usize) -> Vec<usize> {
+    let mut detector = MarkerDetector::new(matching_chars);
+    line.bytes()
+        .enumerate()
+        .filter(|&(_, symbol)| detector.push(symbol))
+        .map(|(index, _)| index + 1)
+        .collect()
 }
 
 #[aoc(day6, part1)]
 pub fn solve_part1(input: &str) -> usize {
-    input
-        .lines()
-        .map(|line| get_first_marker(line, 4))
-        .next()
-        .unwrap()
+    get_first_marker(input.trim(), 4).expect("no start-of-packet marker")
 }
 
 #[aoc(day6, part2)]
 pub fn solve_part2(input: &str) -> usize {
-    input
-        .lines()
-        .map(|line| get_first_marker(line, 14))
-        .next()
-        .unwrap()
+    get_first_marker(input.trim(), 14).expect("no start-of-message marker")
+}
+
+#[cfg(test)]
+mod test {
+    use crate::day6::*;
+
+    const EXAMPLES: &[(&str, usize, usize)] = &[
+        ("mjqjpqmgbljsphdztnvjfqwrcgsmlb", 7, 19),
+        ("bvwbjplbgvbhsrlpgdmjqwftvncz", 5, 23),
+        ("nppdvjthqldpwncqszvftbrmjlhg", 6, 23),
+        ("nznrnfrfntjfmvfwmzdfjlvtqnbhcprsg", 10, 29),
+        ("zcfzfwzzqfrljwzlrfnpqdbhtmscgvjw", 11, 26),
+    ];
+
+    #[test]
+    fn test_examples() {
+        for &(input, part1, part2) in EXAMPLES {
+            assert_eq!(solve_part1(input), part1);
+            assert_eq!(solve_part2(input), part2);
+            assert_eq!(first_marker_in(input.as_bytes(), 14).unwrap(), Some(part2));
+        }
+    }
+
+    #[test]
+    fn test_bitmask() {
+        for &(input, part1, part2) in EXAMPLES {
+            assert_eq!(get_first_marker_bitmask(input, 4), Some(part1));
+            assert_eq!(get_first_marker_bitmask(input, 14), Some(part2));
+        }
+        // Long enough for the vectorized search, with the marker past the first blocks
+        let signal = "abc".repeat(40) + "defg";
+        assert_eq!(get_first_marker_bitmask(&signal, 4), Some(121));
+        assert_eq!(get_first_marker_bitmask(&signal, 5), Some(122));
+        assert_eq!(get_first_marker_bitmask(&signal, 7), Some(124));
+        assert_eq!(get_first_marker_bitmask(&signal, 8), None);
+        // Letters three times in a window, and symbols outside of the mask
+        assert_eq!(get_first_marker_bitmask("aaabcd", 4), Some(6));
+        assert_eq!(get_first_marker_bitmask("ab!AB", 4), Some(4));
+        assert_eq!(get_first_marker_bitmask("a", 27), None);
+        // Spanning several of the chunks the letters are checked by
+        let long = "abc".repeat(3000);
+        for tail in ["defg", "d#fg"] {
+            let signal = long.clone() + tail;
+            assert_eq!(
+                get_first_marker_bitmask(&signal, 4),
+                get_first_marker(&signal, 4)
+            );
+        }
+    }
+
+    #[test]
+    fn test_no_marker() {
+        assert_eq!(get_first_marker("abcabcabc", 4), None);
+        assert_eq!(get_first_marker("ab", 4), None);
+        assert_eq!(first_marker_in(&b"aaaa"[..], 2).unwrap(), None);
+    }
+
+    #[test]
+    fn test_all_markers() {
+        assert_eq!(get_all_markers("abcd", 2), [2, 3, 4]);
+        assert_eq!(get_all_markers("aabaa", 2), [3, 4]);
+        assert_eq!(get_all_markers("abcabcabc", 3), [3, 4, 5, 6, 7, 8, 9]);
+    }
+
+    #[test]
+    fn test_stream_across_reads() {
+        // Chained readers hand the data over in several reads
+        let reader = "mjqjpqmgbl"
+            .as_bytes()
+            .chain("jsphdztnvjfqwrcgsmlb".as_bytes());
+        let markers = Markers::new(reader, 14)
+            .collect::<io::Result<Vec<usize>>>()
+            .unwrap();
+        assert_eq!(markers, get_all_markers(EXAMPLES[0].0, 14));
+        assert_eq!(markers[0], 19);
+    }
 }
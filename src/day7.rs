@@ -1,134 +1,1301 @@
-use std::{collections::HashSet, hash::Hash, rc::Rc};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt, fs, io,
+    path::Path,
+};
 
 use aoc_runner_derive::aoc;
 
-#[derive(PartialEq, Eq, Hash)]
-pub struct Folder {
-    size: usize,
-    path: Rc<Path>,
+pub type NodeId = usize;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NodeKind {
+    Directory { children: Vec<NodeId> },
+    File { size: usize },
 }
 
-#[derive(PartialEq, Eq, Hash)]
-pub struct Path {
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Node {
     name: String,
-    // Represents a stack of directory name, up to /
-    previous_directory: Option<Rc<Path>>,
+    parent: Option<NodeId>,
+    kind: NodeKind,
 }
 
-pub struct BrowsingState {
-    folder_stack: Vec<Folder>,
-    other_folders: HashSet<Folder>,
+impl Node {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn parent(&self) -> Option<NodeId> {
+        self.parent
+    }
+
+    pub fn kind(&self) -> &NodeKind {
+        &self.kind
+    }
+
+    pub fn is_dir(&self) -> bool {
+        matches!(self.kind, NodeKind::Directory { .. })
+    }
 }
 
-impl BrowsingState {
-    pub fn cd_dir(&mut self, line: &str) {
-        let dir_name = line.split(' ').last().expect("dir");
-        match dir_name {
-            ".." => self.pop_folder(),
-            _ => self.push_on_stack(dir_name),
+/// Filesystem stored as an arena of nodes, the root directory `/` being the first one.
+/// Nodes are always added after their parent, which lets sizes be computed in one pass.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FsTree {
+    nodes: Vec<Node>,
+    // Entries by directory and name, directories keep their children in listing order
+    index: HashMap<(NodeId, String), NodeId>,
+}
+
+impl Default for FsTree {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FsTree {
+    pub const ROOT: NodeId = 0;
+
+    pub fn new() -> Self {
+        Self {
+            nodes: vec![Node {
+                name: String::from("/"),
+                parent: None,
+                kind: NodeKind::Directory { children: vec![] },
+            }],
+            index: HashMap::new(),
         }
     }
 
-    pub fn pop_folder(&mut self) {
-        let popped_dir = self.folder_stack.pop().expect("pop");
-        let new_current_folder = self.folder_stack.last_mut();
-        match new_current_folder {
-            Some(f) => f.size += popped_dir.size,
-            None => {}
-        };
-        self.other_folders.insert(popped_dir);
+    pub fn node(&self, id: NodeId) -> &Node {
+        &self.nodes[id]
     }
 
-    pub fn push_on_stack(&mut self, dir_name: &str) {
-        let new_path = match self.folder_stack.last() {
-            Some(folder) => Path {
-                name: folder.path.name.to_string() + "/" + dir_name,
-                previous_directory: Some(Rc::clone(&folder.path)),
-            },
-            None => Path {
-                name: dir_name.to_owned(),
-                previous_directory: None,
-            },
+    /// Number of entries below the root, which is always there.
+    pub fn len(&self) -> usize {
+        self.nodes.len() - 1
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn child(&self, directory: NodeId, name: &str) -> Option<NodeId> {
+        self.index.get(&(directory, name.to_owned())).copied()
+    }
+
+    pub fn children(&self, directory: NodeId) -> impl Iterator<Item = NodeId> + '_ {
+        let children = match &self.nodes[directory].kind {
+            NodeKind::Directory { children } => Some(children.iter().copied()),
+            NodeKind::File { .. } => None,
         };
-        let new_folder = Folder {
-            size: 0,
-            path: Rc::new(new_path),
+        children.into_iter().flatten()
+    }
+
+    fn insert(&mut self, parent: NodeId, name: &str, kind: NodeKind) -> NodeId {
+        if let Some(existing) = self.child(parent, name) {
+            return existing;
+        }
+        let id = self.nodes.len();
+        match &mut self.nodes[parent].kind {
+            NodeKind::Directory { children } => children.push(id),
+            NodeKind::File { .. } => panic!("cannot add {name} to a file"),
         };
-        self.folder_stack.push(new_folder);
+        self.index.insert((parent, name.to_owned()), id);
+        self.nodes.push(Node {
+            name: name.to_owned(),
+            parent: Some(parent),
+            kind,
+        });
+        id
+    }
+
+    /// Adds a directory under `parent`, returning the existing entry if there is one.
+    pub fn add_directory(&mut self, parent: NodeId, name: &str) -> NodeId {
+        self.insert(parent, name, NodeKind::Directory { children: vec![] })
     }
 
-    pub fn add_size(&mut self, line: &str) {
-        let size: usize = line
-            .split(' ')
-            .next()
-            .expect("line empty")
-            .parse()
-            .expect(line);
-        let curr_folder = self.folder_stack.last_mut().expect("curr_folder");
-        curr_folder.size += size;
+    /// Adds a file under `parent`, returning the existing entry if there is one.
+    pub fn add_file(&mut self, parent: NodeId, name: &str, size: usize) -> NodeId {
+        self.insert(parent, name, NodeKind::File { size })
     }
 
-    pub fn back_to_dir(&mut self) {
-        while self.folder_stack.len() > 0 {
-            self.pop_folder()
+    /// Finds a node from its absolute path, such as `/a/e/i`.
+    pub fn lookup(&self, path: &str) -> Option<NodeId> {
+        path.strip_prefix('/')?
+            .split('/')
+            .filter(|name| !name.is_empty())
+            .try_fold(Self::ROOT, |directory, name| self.child(directory, name))
+    }
+
+    pub fn path(&self, id: NodeId) -> String {
+        let mut names = vec![];
+        let mut current = id;
+        while let Some(parent) = self.nodes[current].parent {
+            names.push(self.nodes[current].name.as_str());
+            current = parent;
         }
+        names.reverse();
+        format!("/{}", names.join("/"))
     }
 
-    pub fn get_under_size(&self, size: usize) -> usize {
-        self.other_folders
+    /// Depth-first iteration from the root, entries of a directory in listing order.
+    pub fn iter(&self) -> impl Iterator<Item = (NodeId, &Node)> + '_ {
+        self.iter_with_depth().map(|(id, node, _)| (id, node))
+    }
+
+    /// Same as `iter`, along with the depth of each node, the root being at depth 0.
+    pub fn iter_with_depth(&self) -> impl Iterator<Item = (NodeId, &Node, usize)> + '_ {
+        let mut stack = vec![(Self::ROOT, 0)];
+        std::iter::from_fn(move || {
+            let (id, depth) = stack.pop()?;
+            let mut children = self.children(id).collect::<Vec<NodeId>>();
+            children.reverse();
+            stack.extend(children.into_iter().map(|child| (child, depth + 1)));
+            Some((id, &self.nodes[id], depth))
+        })
+    }
+
+    /// Recursive size of every node, indexed by node id, like `du` does.
+    pub fn du(&self) -> Vec<usize> {
+        let mut sizes = self
+            .nodes
             .iter()
-            .filter(|f| f.size <= size)
-            .map(|f| f.size)
+            .map(|node| match node.kind {
+                NodeKind::File { size } => size,
+                NodeKind::Directory { .. } => 0,
+            })
+            .collect::<Vec<usize>>();
+        for id in (1..self.nodes.len()).rev() {
+            let parent = self.nodes[id].parent.expect("only the root has no parent");
+            sizes[parent] += sizes[id];
+        }
+        sizes
+    }
+
+    pub fn size(&self, id: NodeId) -> usize {
+        match self.nodes[id].kind {
+            NodeKind::File { size } => size,
+            NodeKind::Directory { .. } => self.children(id).map(|child| self.size(child)).sum(),
+        }
+    }
+
+    /// Recursive sizes of all the directories, root included.
+    pub fn directory_sizes(&self) -> impl Iterator<Item = (NodeId, usize)> + '_ {
+        self.du()
+            .into_iter()
+            .enumerate()
+            .filter(|&(id, _)| self.nodes[id].is_dir())
+    }
+
+    pub fn get_under_size(&self, size: usize) -> usize {
+        self.directory_sizes()
+            .map(|(_, dir_size)| dir_size)
+            .filter(|&dir_size| dir_size <= size)
             .sum()
     }
 
     pub fn get_total_size(&self) -> usize {
-        self.other_folders
-            .iter()
-            .find(|f| f.path.name == "/")
-            .expect("could not find root dir")
-            .size
+        self.du()[Self::ROOT]
     }
 
-    pub fn find_smallest(&self, size_to_free: usize) -> &Folder {
-        self.other_folders
-            .iter()
-            .filter(|f| f.size >= size_to_free)
-            .min_by_key(|f| f.size)
-            .expect("could not find min")
+    pub fn find_smallest(&self, size_to_free: usize) -> Option<(NodeId, usize)> {
+        self.directory_sizes()
+            .filter(|&(_, dir_size)| dir_size >= size_to_free)
+            .min_by_key(|&(_, dir_size)| dir_size)
+    }
+}
+
+/// Renders the tree the way the puzzle does, e.g. `- a (dir)` and `- i (file, size=584)`.
+impl fmt::Display for FsTree {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (_, node, depth) in self.iter_with_depth() {
+            let description = match node.kind {
+                NodeKind::Directory { .. } => String::from("dir"),
+                NodeKind::File { size } => format!("file, size={size}"),
+            };
+            writeln!(f, "{}- {} ({description})", "  ".repeat(depth), node.name)?;
+        }
+        Ok(())
+    }
+}
+
+/// Size in the style of `du -h`: powers of 1024, rounded up, with one decimal under 10.
+pub fn human_size(size: usize) -> String {
+    let mut value = size as f64;
+    let mut units = ["K", "M", "G", "T", "P"].iter();
+    let mut unit = "";
+    while value >= 1024.0 {
+        match units.next() {
+            Some(next) => unit = next,
+            None => break,
+        }
+        value /= 1024.0;
+    }
+    if unit.is_empty() {
+        size.to_string()
+    } else if value < 10.0 {
+        format!("{:.1}{unit}", (value * 10.0).ceil() / 10.0)
+    } else {
+        format!("{}{unit}", value.ceil())
+    }
+}
+
+impl FsTree {
+    /// One `size<TAB>path` line per directory, biggest first, like `du -h | sort -rh`.
+    pub fn du_listing(&self) -> String {
+        let mut directories = self.directory_sizes().collect::<Vec<(NodeId, usize)>>();
+        directories.sort_by_key(|&(id, size)| (std::cmp::Reverse(size), self.path(id)));
+        directories
+            .into_iter()
+            .map(|(id, size)| format!("{}\t{}\n", human_size(size), self.path(id)))
+            .collect()
+    }
+
+    /// Folded stacks (`/;a;e;i 584`), one line per non-empty file, for flame graph tools.
+    pub fn folded_stacks(&self) -> String {
+        let mut output = String::new();
+        for (_, node) in self.iter() {
+            if let NodeKind::File { size: size @ 1.. } = node.kind {
+                let mut frames = vec![node.name.as_str()];
+                let mut current = node.parent;
+                while let Some(parent) = current {
+                    frames.push(&self.nodes[parent].name);
+                    current = self.nodes[parent].parent;
+                }
+                frames.reverse();
+                output.push_str(&format!("{} {size}\n", frames.join(";")));
+            }
+        }
+        output
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiagnosticKind {
+    UnknownCommand(String),
+    MalformedCommand(String),
+    MalformedEntry(String),
+    OutputWithoutLs(String),
+    RepeatedListing { path: String },
+    ConflictingEntry { path: String },
+    CdIntoUnlisted { path: String },
+    CdIntoFile { path: String },
+    CdAboveRoot,
+}
+
+/// Problem found on a line of a transcript, the line being 1-based.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub line: usize,
+    pub kind: DiagnosticKind,
+}
+
+impl Diagnostic {
+    /// Errors are lines that had to be ignored, other diagnostics are only warnings about
+    /// a transcript that could still be replayed.
+    pub fn is_error(&self) -> bool {
+        !matches!(
+            self.kind,
+            DiagnosticKind::RepeatedListing { .. }
+                | DiagnosticKind::CdIntoUnlisted { .. }
+                | DiagnosticKind::CdAboveRoot
+        )
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: ", self.line)?;
+        match &self.kind {
+            DiagnosticKind::UnknownCommand(command) => write!(f, "unknown command `{command}`"),
+            DiagnosticKind::MalformedCommand(line) => write!(f, "malformed command `{line}`"),
+            DiagnosticKind::MalformedEntry(line) => write!(f, "malformed ls output `{line}`"),
+            DiagnosticKind::OutputWithoutLs(line) => write!(f, "output `{line}` outside of ls"),
+            DiagnosticKind::RepeatedListing { path } => {
+                write!(f, "{path} was already listed, entries are deduplicated")
+            }
+            DiagnosticKind::ConflictingEntry { path } => {
+                write!(f, "{path} was listed before with another type or size")
+            }
+            DiagnosticKind::CdIntoUnlisted { path } => {
+                write!(f, "cd into {path} which was never listed")
+            }
+            DiagnosticKind::CdIntoFile { path } => write!(f, "cd into file {path}"),
+            DiagnosticKind::CdAboveRoot => write!(f, "cd .. from /"),
+        }
+    }
+}
+
+/// Replays a terminal transcript, keeping track of the current directory and of the
+/// problems found on the way.
+pub struct BrowsingState {
+    tree: FsTree,
+    current_directory: NodeId,
+    // Directory whose ls output is being read, if any
+    listing: Option<NodeId>,
+    listed: HashSet<NodeId>,
+    line: usize,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Default for BrowsingState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BrowsingState {
+    pub fn new() -> Self {
+        Self {
+            tree: FsTree::new(),
+            current_directory: FsTree::ROOT,
+            listing: None,
+            listed: HashSet::new(),
+            line: 0,
+            diagnostics: vec![],
+        }
+    }
+
+    pub fn tree(&self) -> &FsTree {
+        &self.tree
+    }
+
+    pub fn into_tree(self) -> FsTree {
+        self.tree
+    }
+
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.diagnostics.iter().any(Diagnostic::is_error)
+    }
+
+    fn diagnose(&mut self, kind: DiagnosticKind) {
+        self.diagnostics.push(Diagnostic {
+            line: self.line,
+            kind,
+        });
+    }
+
+    pub fn read_line(&mut self, line: &str) {
+        self.line += 1;
+        let line = line.trim_end();
+        if line.is_empty() {
+            return;
+        }
+        let Some(command) = line.strip_prefix('$') else {
+            match self.listing {
+                Some(_) => self.add_entry(line),
+                None => self.diagnose(DiagnosticKind::OutputWithoutLs(line.to_owned())),
+            }
+            return;
+        };
+
+        self.listing = None;
+        // Directory names may contain spaces, everything after `cd ` is the name
+        let command = command.trim_start();
+        match command.split_once(' ').unwrap_or((command, "")) {
+            ("ls", "") => self.ls(),
+            ("cd", dir_name) if !dir_name.is_empty() => self.cd_dir(dir_name),
+            ("cd" | "ls" | "", _) => {
+                self.diagnose(DiagnosticKind::MalformedCommand(line.to_owned()))
+            }
+            (unknown, _) => self.diagnose(DiagnosticKind::UnknownCommand(unknown.to_string())),
+        }
+    }
+
+    pub fn ls(&mut self) {
+        if !self.listed.insert(self.current_directory) {
+            let path = self.tree.path(self.current_directory);
+            self.diagnose(DiagnosticKind::RepeatedListing { path });
+        }
+        self.listing = Some(self.current_directory);
+    }
+
+    pub fn cd_dir(&mut self, dir_name: &str) {
+        self.current_directory = match dir_name {
+            "/" => FsTree::ROOT,
+            ".." => match self.tree.node(self.current_directory).parent() {
+                Some(parent) => parent,
+                None => {
+                    self.diagnose(DiagnosticKind::CdAboveRoot);
+                    FsTree::ROOT
+                }
+            },
+            _ => match self.tree.child(self.current_directory, dir_name) {
+                Some(child) if self.tree.node(child).is_dir() => child,
+                Some(child) => {
+                    let path = self.tree.path(child);
+                    self.diagnose(DiagnosticKind::CdIntoFile { path });
+                    self.current_directory
+                }
+                None => {
+                    let child = self.tree.add_directory(self.current_directory, dir_name);
+                    let path = self.tree.path(child);
+                    self.diagnose(DiagnosticKind::CdIntoUnlisted { path });
+                    child
+                }
+            },
+        }
+    }
+
+    pub fn add_entry(&mut self, line: &str) {
+        let directory = self.listing.unwrap_or(self.current_directory);
+        let kind = match line.split_once(' ') {
+            Some((_, "")) | None => None,
+            Some(("dir", name)) => Some((name, NodeKind::Directory { children: vec![] })),
+            Some((size, name)) => size
+                .parse()
+                .ok()
+                .map(|size| (name, NodeKind::File { size })),
+        };
+        let Some((name, kind)) = kind else {
+            return self.diagnose(DiagnosticKind::MalformedEntry(line.to_owned()));
+        };
+
+        match self.tree.child(directory, name) {
+            Some(existing) => {
+                let same_entry = match (self.tree.node(existing).kind(), &kind) {
+                    (NodeKind::File { size }, NodeKind::File { size: listed }) => size == listed,
+                    (NodeKind::Directory { .. }, NodeKind::Directory { .. }) => true,
+                    _ => false,
+                };
+                if !same_entry {
+                    let path = self.tree.path(existing);
+                    self.diagnose(DiagnosticKind::ConflictingEntry { path });
+                }
+            }
+            None => {
+                self.tree.insert(directory, name, kind);
+            }
+        }
     }
 }
 
+/// Replays a whole transcript, problems being reported by `BrowsingState::diagnostics`.
 pub fn parse_folders(input: &str) -> BrowsingState {
-    let folder_stack: Vec<Folder> = vec![];
-    let other_folders: HashSet<Folder> = HashSet::new();
-    let mut browsing_state = BrowsingState {
-        folder_stack,
-        other_folders,
-    };
+    let mut browsing_state = BrowsingState::new();
     for line in input.lines() {
-        match &line[..4] {
-            // "push or pop on stack"
-            "$ cd" => browsing_state.cd_dir(line),
-            "$ ls" => {}
-            "dir " => {}
-            _ => browsing_state.add_size(line),
+        browsing_state.read_line(line);
+    }
+    browsing_state
+}
+
+/// Writes the `$ cd` / `$ ls` session exploring the whole tree, each directory being
+/// listed before visiting its subdirectories in order.
+pub fn transcript_from_tree(tree: &FsTree) -> String {
+    let mut transcript = String::from("$ cd /\n");
+    // Directories still to visit, `None` standing for going back up
+    let mut stack = vec![Some(FsTree::ROOT)];
+    while let Some(step) = stack.pop() {
+        let Some(directory) = step else {
+            transcript.push_str("$ cd ..\n");
+            continue;
         };
+        if directory != FsTree::ROOT {
+            transcript.push_str(&format!("$ cd {}\n", tree.node(directory).name()));
+            stack.push(None);
+        }
+        transcript.push_str("$ ls\n");
+        let mut subdirectories = vec![];
+        for child in tree.children(directory) {
+            let node = tree.node(child);
+            match node.kind() {
+                NodeKind::Directory { .. } => {
+                    transcript.push_str(&format!("dir {}\n", node.name()));
+                    subdirectories.push(Some(child));
+                }
+                NodeKind::File { size } => {
+                    transcript.push_str(&format!("{size} {}\n", node.name()));
+                }
+            }
+        }
+        stack.extend(subdirectories.into_iter().rev());
     }
+    transcript
+}
 
-    browsing_state.back_to_dir();
-    browsing_state
+/// Reads a directory on disk into a tree, entries sorted by name. Symbolic links are not
+/// followed and are left out, as are entries whose names cannot appear in a transcript.
+pub fn tree_from_disk(root: &Path) -> io::Result<FsTree> {
+    let mut tree = FsTree::new();
+    let mut to_visit = vec![(root.to_path_buf(), FsTree::ROOT)];
+    while let Some((path, directory)) = to_visit.pop() {
+        let mut entries = fs::read_dir(&path)?.collect::<io::Result<Vec<fs::DirEntry>>>()?;
+        entries.sort_by_key(|entry| entry.file_name());
+        for entry in entries {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if name.contains('\n') || name.trim_end() != name {
+                continue;
+            }
+            let metadata = entry.metadata()?;
+            if metadata.is_dir() {
+                let child = tree.add_directory(directory, &name);
+                to_visit.push((entry.path(), child));
+            } else if metadata.is_file() {
+                tree.add_file(directory, &name, metadata.len() as usize);
+            }
+        }
+    }
+    Ok(tree)
+}
+
+pub fn transcript_from_disk(root: &Path) -> io::Result<String> {
+    Ok(transcript_from_tree(&tree_from_disk(root)?))
+}
+
+pub const SMALL_DIRECTORY_SIZE: usize = 100000;
+pub const DISK_CAPACITY: usize = 70000000;
+pub const REQUIRED_FREE_SPACE: usize = 30000000;
+
+/// Directories to delete and the space their deletion frees.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Deletion {
+    pub directories: Vec<NodeId>,
+    pub freed: usize,
+    /// Whether the deletion is the best one for what was asked, rather than a fallback.
+    pub exact: bool,
+}
+
+/// Decides what to delete from a filesystem so that enough space is free on the disk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DiskPlanner {
+    capacity: usize,
+    required_free_space: usize,
+}
+
+impl Default for DiskPlanner {
+    fn default() -> Self {
+        Self::new(DISK_CAPACITY, REQUIRED_FREE_SPACE)
+    }
+}
+
+impl DiskPlanner {
+    pub fn new(capacity: usize, required_free_space: usize) -> Self {
+        Self {
+            capacity,
+            required_free_space,
+        }
+    }
+
+    /// Space that still has to be freed, 0 when there already is enough.
+    pub fn space_to_free(&self, tree: &FsTree) -> usize {
+        let free_space = self.capacity.saturating_sub(tree.get_total_size());
+        self.required_free_space.saturating_sub(free_space)
+    }
+
+    /// Smallest single directory freeing enough space, if any.
+    pub fn smallest_directory(&self, tree: &FsTree) -> Option<Deletion> {
+        let (id, freed) = tree.find_smallest(self.space_to_free(tree))?;
+        Some(Deletion {
+            directories: vec![id],
+            freed,
+            exact: true,
+        })
+    }
+
+    /// Set of non-nested directories freeing enough space while deleting as little data as
+    /// possible, preferring the highest directories on ties.
+    ///
+    /// The search is exact as long as either the number of distinct sums or the bytes to
+    /// free stay under fixed limits, which keeps memory bounded whatever the sizes are.
+    /// Past both limits, the smallest single directory is returned instead, flagged as not
+    /// exact.
+    pub fn optimal_deletion(&self, tree: &FsTree) -> Option<Deletion> {
+        let target = self.space_to_free(tree);
+        if target == 0 {
+            return Some(Deletion {
+                directories: vec![],
+                freed: 0,
+                exact: true,
+            });
+        }
+        let smallest = self.smallest_directory(tree)?;
+        let du = tree.du();
+        sparse_deletion(tree, &du, target)
+            .or_else(|| {
+                (smallest.freed <= BITSET_SUM_LIMIT)
+                    .then(|| bitset_deletion(tree, &du, target, smallest.freed))
+                    .flatten()
+            })
+            .or(Some(Deletion {
+                exact: false,
+                ..smallest
+            }))
+    }
+}
+
+/// Most sums kept by the sparse search, both overall and for a single combination.
+const SPARSE_SUM_LIMIT: usize = 1 << 16;
+/// Largest sum tracked by the bitset search, four bytes per sum being used to rebuild paths.
+const BITSET_SUM_LIMIT: usize = 1 << 24;
+
+/// Exact search keeping, for each subtree, the sorted sums its directories can free. Sums
+/// past the target are only worth their smallest one. Gives up past `SPARSE_SUM_LIMIT`.
+fn sparse_deletion(tree: &FsTree, du: &[usize], target: usize) -> Option<Deletion> {
+    // Children come after their parent in the arena
+    let mut sums: Vec<Vec<usize>> = vec![vec![]; tree.nodes.len()];
+    let mut stored = 0;
+    for id in (0..tree.nodes.len()).rev() {
+        if tree.nodes[id].is_dir() {
+            let mut subtree = children_sums(tree, id, &sums, target)?.pop().unwrap();
+            subtree.push(du[id]);
+            sums[id] = reachable(subtree, target);
+            stored += sums[id].len();
+            if stored > SPARSE_SUM_LIMIT {
+                return None;
+            }
+        }
+    }
+
+    let freed = *sums[FsTree::ROOT]
+        .last()
+        .filter(|&&freed| freed >= target)?;
+    let mut directories = vec![];
+    collect_deletion(
+        tree,
+        FsTree::ROOT,
+        freed,
+        target,
+        du,
+        &sums,
+        &mut directories,
+    );
+    directories.reverse();
+    Some(Deletion {
+        directories,
+        freed,
+        exact: true,
+    })
+}
+
+/// Sums freed by the first children directories, from none of them to all of them.
+fn children_sums(
+    tree: &FsTree,
+    directory: NodeId,
+    sums: &[Vec<usize>],
+    target: usize,
+) -> Option<Vec<Vec<usize>>> {
+    let mut prefixes = vec![vec![0]];
+    for child in tree.children(directory) {
+        if tree.nodes[child].is_dir() {
+            let previous = prefixes.last().unwrap();
+            if previous.len() * sums[child].len() > SPARSE_SUM_LIMIT {
+                return None;
+            }
+            let combined = previous
+                .iter()
+                .flat_map(|&sum| sums[child].iter().map(move |&freed| sum + freed))
+                .collect();
+            prefixes.push(reachable(combined, target));
+        }
+    }
+    Some(prefixes)
+}
+
+/// Finds back directories of the subtree freeing exactly `freed`, in reverse order.
+fn collect_deletion(
+    tree: &FsTree,
+    directory: NodeId,
+    mut freed: usize,
+    target: usize,
+    du: &[usize],
+    sums: &[Vec<usize>],
+    directories: &mut Vec<NodeId>,
+) {
+    if freed == 0 {
+        return;
+    }
+    if freed == du[directory] {
+        directories.push(directory);
+        return;
+    }
+    let prefixes = children_sums(tree, directory, sums, target)
+        .expect("sums were combined within limits already");
+    let children = tree
+        .children(directory)
+        .filter(|&child| tree.nodes[child].is_dir())
+        .collect::<Vec<NodeId>>();
+    for (index, &child) in children.iter().enumerate().rev() {
+        let from_child = *sums[child]
+            .iter()
+            .find(|&&sum| sum <= freed && prefixes[index].binary_search(&(freed - sum)).is_ok())
+            .expect("a sum is only reached through the children");
+        collect_deletion(tree, child, from_child, target, du, sums, directories);
+        freed -= from_child;
+    }
+}
+
+/// Sorts and deduplicates sums, dropping the ones past the smallest sum reaching `target`.
+fn reachable(mut sums: Vec<usize>, target: usize) -> Vec<usize> {
+    sums.sort_unstable();
+    sums.dedup();
+    let reaching = sums.partition_point(|&sum| sum < target);
+    sums.truncate(reaching + 1);
+    sums
+}
+
+/// Exact search with one bit per sum up to `bound`, which is worth it for small sizes
+/// whatever the number of directories.
+fn bitset_deletion(tree: &FsTree, du: &[usize], target: usize, bound: usize) -> Option<Deletion> {
+    // Directories in depth-first order, each one with the position following its
+    // subtree: choosing a directory means jumping there, skipping it means going
+    // to the next position. Every path to the end is a set of non-nested directories.
+    let mut order = vec![];
+    let mut subtree_end = vec![];
+    let mut open: Vec<(usize, usize)> = vec![];
+    for (id, node, depth) in tree.iter_with_depth() {
+        if !node.is_dir() {
+            continue;
+        }
+        while open
+            .last()
+            .is_some_and(|&(_, open_depth)| open_depth >= depth)
+        {
+            subtree_end[open.pop().unwrap().0] = order.len();
+        }
+        open.push((order.len(), depth));
+        order.push(id);
+        subtree_end.push(0);
+    }
+    for (position, _) in open {
+        subtree_end[position] = order.len();
+    }
+
+    // Sums freed by the paths reaching a position only grow along the way, so the
+    // first position reaching each sum is enough to rebuild a path afterwards
+    let mut first_reached = vec![u32::MAX; bound + 1];
+    first_reached[0] = 0;
+    let mut reached = SumSet::new(bound);
+    reached.insert(0);
+    let mut jumps: HashMap<usize, SumSet> = HashMap::new();
+    for (position, &id) in order.iter().enumerate() {
+        jumps
+            .entry(subtree_end[position])
+            .or_insert_with(|| SumSet::new(bound))
+            .union_shifted(&reached, du[id]);
+        if let Some(jumped) = jumps.remove(&(position + 1)) {
+            for sum in reached.union_new(&jumped) {
+                first_reached[sum] = (position + 1) as u32;
+            }
+        }
+    }
+
+    let freed = reached.first_from(target)?;
+    let mut directories = vec![];
+    let mut remaining = freed;
+    while remaining > 0 {
+        let position = first_reached[remaining] as usize;
+        let chosen = (0..position)
+            .find(|&from| {
+                subtree_end[from] == position
+                    && du[order[from]] <= remaining
+                    && (first_reached[remaining - du[order[from]]] as usize) <= from
+            })
+            .expect("a sum is only reached through a jump");
+        directories.push(order[chosen]);
+        remaining -= du[order[chosen]];
+    }
+    directories.reverse();
+    Some(Deletion {
+        directories,
+        freed,
+        exact: true,
+    })
+}
+
+/// Set of sums between 0 and a bound, stored as a bitset.
+struct SumSet {
+    words: Vec<u64>,
+    bound: usize,
+}
+
+impl SumSet {
+    fn new(bound: usize) -> Self {
+        Self {
+            words: vec![0; bound / 64 + 1],
+            bound,
+        }
+    }
+
+    fn insert(&mut self, sum: usize) {
+        self.words[sum / 64] |= 1 << (sum % 64);
+    }
+
+    /// Adds every sum of `other` increased by `shift`, as long as it stays in bounds.
+    fn union_shifted(&mut self, other: &SumSet, shift: usize) {
+        if shift > self.bound {
+            return;
+        }
+        let (word_shift, bit_shift) = (shift / 64, shift % 64);
+        for index in (word_shift..self.words.len()).rev() {
+            let source = index - word_shift;
+            let mut word = other.words[source] << bit_shift;
+            if bit_shift > 0 && source > 0 {
+                word |= other.words[source - 1] >> (64 - bit_shift);
+            }
+            self.words[index] |= word;
+        }
+        let last_bits = self.bound % 64 + 1;
+        if last_bits < 64 {
+            *self.words.last_mut().unwrap() &= (1 << last_bits) - 1;
+        }
+    }
+
+    /// Smallest sum of the set greater or equal to `start`.
+    fn first_from(&self, start: usize) -> Option<usize> {
+        let mut index = start / 64;
+        let mut word = *self.words.get(index)? & (u64::MAX << (start % 64));
+        while word == 0 {
+            index += 1;
+            word = *self.words.get(index)?;
+        }
+        Some(index * 64 + word.trailing_zeros() as usize)
+    }
+
+    /// Adds the sums of `other`, returning the ones that were not in the set yet.
+    fn union_new(&mut self, other: &SumSet) -> Vec<usize> {
+        let mut added = vec![];
+        for (index, (word, other_word)) in self.words.iter_mut().zip(&other.words).enumerate() {
+            let mut new_bits = other_word & !*word;
+            *word |= other_word;
+            while new_bits != 0 {
+                added.push(index * 64 + new_bits.trailing_zeros() as usize);
+                new_bits &= new_bits - 1;
+            }
+        }
+        added
+    }
 }
 
 #[aoc(day7, part1)]
 pub fn solve_part1(input: &str) -> usize {
-    let browsing_state = parse_folders(input);
-    browsing_state.get_under_size(100000)
+    let tree = parse_folders(input).into_tree();
+    tree.get_under_size(SMALL_DIRECTORY_SIZE)
 }
 
 #[aoc(day7, part2)]
 pub fn solve_part2(input: &str) -> usize {
-    let browsing_state = parse_folders(input);
-    let size_to_free = browsing_state.get_total_size() - 40000000;
-    browsing_state.find_smallest(size_to_free).size
+    let tree = parse_folders(input).into_tree();
+    DiskPlanner::default()
+        .smallest_directory(&tree)
+        .expect("could not find min")
+        .freed
+}
+
+#[cfg(test)]
+mod test {
+    use crate::day7::*;
+
+    const INPUT: &str = "$ cd /
+$ ls
+dir a
+14848514 b.txt
+8504156 c.dat
+dir d
+$ cd a
+$ ls
+dir e
+29116 f
+2557 g
+62596 h.lst
+$ cd e
+$ ls
+584 i
+$ cd ..
+$ cd ..
+$ cd d
+$ ls
+4060174 j
+8033020 d.log
+5626152 d.ext
+7214296 k";
+
+    #[test]
+    fn test_p1() {
+        assert_eq!(solve_part1(INPUT), 95437);
+    }
+
+    #[test]
+    fn test_p2() {
+        assert_eq!(solve_part2(INPUT), 24933642);
+    }
+
+    #[test]
+    fn test_tree() {
+        let tree = parse_folders(INPUT).into_tree();
+        let du = tree.du();
+        for (path, size) in [
+            ("/a/e", 584),
+            ("/a", 94853),
+            ("/d", 24933642),
+            ("/", 48381165),
+        ] {
+            let id = tree.lookup(path).unwrap();
+            assert_eq!(du[id], size);
+            assert_eq!(tree.size(id), size);
+            assert_eq!(tree.path(id), path);
+        }
+        assert_eq!(tree.lookup("/a/e/i").map(|id| du[id]), Some(584));
+        assert_eq!(tree.lookup("/a/x"), None);
+        assert_eq!(tree.len(), 13);
+        assert!(FsTree::new().is_empty());
+        assert_eq!(
+            tree.iter()
+                .map(|(id, _)| tree.path(id))
+                .collect::<Vec<String>>(),
+            [
+                "/", "/a", "/a/e", "/a/e/i", "/a/f", "/a/g", "/a/h.lst", "/b.txt", "/c.dat", "/d",
+                "/d/j", "/d/d.log", "/d/d.ext", "/d/k"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_render_tree() {
+        let tree = parse_folders(INPUT).into_tree();
+        assert_eq!(
+            tree.to_string(),
+            "- / (dir)
+  - a (dir)
+    - e (dir)
+      - i (file, size=584)
+    - f (file, size=29116)
+    - g (file, size=2557)
+    - h.lst (file, size=62596)
+  - b.txt (file, size=14848514)
+  - c.dat (file, size=8504156)
+  - d (dir)
+    - j (file, size=4060174)
+    - d.log (file, size=8033020)
+    - d.ext (file, size=5626152)
+    - k (file, size=7214296)
+"
+        );
+        assert_eq!(tree.du_listing(), "47M\t/\n24M\t/d\n93K\t/a\n584\t/a/e\n");
+        assert_eq!(
+            tree.folded_stacks().lines().take(3).collect::<Vec<&str>>(),
+            ["/;a;e;i 584", "/;a;f 29116", "/;a;g 2557"]
+        );
+        assert_eq!(human_size(1024), "1.0K");
+        assert_eq!(human_size(1100), "1.1K");
+        assert_eq!(human_size(3 * 1024 * 1024 * 1024), "3.0G");
+    }
+
+    #[test]
+    fn test_cd_root_mid_session() {
+        let tree = parse_folders(
+            "$ cd /
+$ ls
+dir a
+$ cd a
+$ ls
+dir b
+10 x
+$ cd b
+$ cd /
+$ ls
+dir a
+20 y",
+        )
+        .into_tree();
+        assert_eq!(tree.get_total_size(), 30);
+        assert_eq!(tree.lookup("/y").map(|id| tree.size(id)), Some(20));
+        assert_eq!(tree.lookup("/a/b").map(|id| tree.size(id)), Some(0));
+    }
+
+    #[test]
+    fn test_disk_planner() {
+        let tree = parse_folders(INPUT).into_tree();
+        let planner = DiskPlanner::default();
+        assert_eq!(planner.space_to_free(&tree), 8381165);
+        let d = tree.lookup("/d").unwrap();
+        assert_eq!(
+            planner.optimal_deletion(&tree),
+            Some(Deletion {
+                directories: vec![d],
+                freed: 24933642,
+                exact: true,
+            })
+        );
+
+        // Files cannot be deleted on their own, so /a is still the best choice
+        let planner = DiskPlanner::new(48381165 + 10000, 100000);
+        assert_eq!(planner.space_to_free(&tree), 90000);
+        assert_eq!(planner.smallest_directory(&tree).unwrap().freed, 94853);
+        assert_eq!(planner.optimal_deletion(&tree).unwrap().freed, 94853);
+
+        assert_eq!(
+            DiskPlanner::new(100000000, 30000000).optimal_deletion(&tree),
+            Some(Deletion {
+                directories: vec![],
+                freed: 0,
+                exact: true,
+            })
+        );
+        assert_eq!(DiskPlanner::new(1, 100000000).optimal_deletion(&tree), None);
+    }
+
+    #[test]
+    fn test_optimal_deletion_combines_directories() {
+        let tree = parse_folders(
+            "$ cd /
+$ ls
+dir a
+dir b
+dir c
+$ cd a
+$ ls
+30 x
+$ cd ..
+$ cd b
+$ ls
+dir d
+$ cd d
+$ ls
+30 y
+$ cd /
+$ cd c
+$ ls
+100 z",
+        )
+        .into_tree();
+        let planner = DiskPlanner::new(200, 95);
+        assert_eq!(planner.space_to_free(&tree), 55);
+        assert_eq!(planner.smallest_directory(&tree).unwrap().freed, 100);
+
+        let mut deletion = planner.optimal_deletion(&tree).unwrap();
+        deletion.directories.sort();
+        assert_eq!(deletion.freed, 60);
+        assert_eq!(
+            deletion.directories,
+            [tree.lookup("/a").unwrap(), tree.lookup("/b").unwrap()]
+        );
+        assert_eq!(
+            bitset_deletion(&tree, &tree.du(), 55, 100),
+            sparse_deletion(&tree, &tree.du(), 55)
+        );
+    }
+
+    #[test]
+    fn test_optimal_deletion_disk_sizes() {
+        const GIB: usize = 1 << 30;
+        let mut tree = FsTree::new();
+        let a = tree.add_directory(FsTree::ROOT, "a");
+        tree.add_file(a, "x", 60 * GIB);
+        let b = tree.add_directory(FsTree::ROOT, "b");
+        tree.add_file(b, "y", 45 * GIB);
+        let z = tree.add_directory(b, "z");
+        tree.add_file(z, "w", 20 * GIB);
+        let c = tree.add_directory(FsTree::ROOT, "c");
+        tree.add_file(c, "v", 130 * GIB);
+        let d = tree.add_directory(FsTree::ROOT, "d");
+        let e = tree.add_directory(d, "e");
+        tree.add_file(e, "u", 3 * GIB);
+        let f = tree.add_directory(d, "f");
+        tree.add_file(f, "t", GIB + 5);
+
+        let planner = DiskPlanner::new(500 * GIB, 303 * GIB);
+        assert_eq!(planner.space_to_free(&tree), 62 * GIB + 5);
+        assert_eq!(planner.smallest_directory(&tree).unwrap().freed, 65 * GIB);
+        assert_eq!(
+            planner.optimal_deletion(&tree),
+            Some(Deletion {
+                directories: vec![a, e],
+                freed: 63 * GIB,
+                exact: true,
+            })
+        );
+
+        // Every subset of the small directories frees a different amount, too many to track,
+        // so the planner settles for the big one although two small ones free less
+        const MIB: usize = 1 << 20;
+        let mut tree = FsTree::new();
+        let small = (0..17)
+            .map(|index| {
+                let directory = tree.add_directory(FsTree::ROOT, &format!("d{index}"));
+                tree.add_file(directory, "data", (MIB << index) + index);
+                directory
+            })
+            .collect::<Vec<NodeId>>();
+        let small_total = tree.get_total_size();
+        let big = tree.add_directory(FsTree::ROOT, "big");
+        tree.add_file(big, "data", small_total);
+
+        let planner = DiskPlanner::new(tree.get_total_size(), small_total / 4 * 3);
+        let target = planner.space_to_free(&tree);
+        assert_eq!(sparse_deletion(&tree, &tree.du(), target), None);
+        let deletion = planner.optimal_deletion(&tree).unwrap();
+        assert_eq!(deletion.directories, [big]);
+        assert!(!deletion.exact);
+        let du = tree.du();
+        let pair = du[small[15]] + du[small[16]];
+        assert!(target <= pair && pair < deletion.freed);
+    }
+
+    #[test]
+    fn test_synthetic_round_trip() {
+        let mut tree = FsTree::new();
+        // Deep: a chain of nested directories with a file at each level
+        let mut directory = FsTree::ROOT;
+        for level in 0..500 {
+            tree.add_file(directory, &format!("file {level}.txt"), level * 3);
+            directory = tree.add_directory(directory, &format!("level {level}"));
+        }
+        // Wide: many siblings, some of them empty
+        let wide = tree.add_directory(FsTree::ROOT, "wide");
+        for index in 0..2000 {
+            let child = tree.add_directory(wide, &format!("d{index}"));
+            if index % 3 != 0 {
+                tree.add_file(child, "data", index);
+            }
+            tree.add_file(wide, &format!("f{index}"), 2 * index);
+        }
+
+        let transcript = transcript_from_tree(&tree);
+        let browsing_state = parse_folders(&transcript);
+        assert!(browsing_state.diagnostics().is_empty());
+        let parsed = browsing_state.into_tree();
+
+        let entries = |tree: &FsTree| {
+            let du = tree.du();
+            tree.iter()
+                .map(|(id, node)| (tree.path(id), node.is_dir(), du[id]))
+                .collect::<Vec<(String, bool, usize)>>()
+        };
+        assert_eq!(entries(&parsed), entries(&tree));
+        assert_eq!(transcript_from_tree(&parsed), transcript);
+    }
+
+    #[test]
+    fn test_disk_round_trip() {
+        let root = std::env::temp_dir().join(format!("advent2022-day7-{}", std::process::id()));
+        let deep = (0..40).fold(root.join("deep"), |path, level| {
+            path.join(format!("l{level}"))
+        });
+        fs::create_dir_all(&deep).unwrap();
+        fs::write(deep.join("bottom.bin"), vec![0; 4096]).unwrap();
+        fs::create_dir_all(root.join("wide")).unwrap();
+        fs::create_dir_all(root.join("empty dir")).unwrap();
+        for index in 0..300 {
+            fs::write(root.join("wide").join(format!("f{index}")), vec![1; index]).unwrap();
+        }
+        fs::write(root.join("a file with spaces"), "hello").unwrap();
+
+        fn disk_usage(path: &std::path::Path) -> usize {
+            let metadata = fs::metadata(path).unwrap();
+            if metadata.is_file() {
+                return metadata.len() as usize;
+            }
+            fs::read_dir(path)
+                .unwrap()
+                .map(|entry| disk_usage(&entry.unwrap().path()))
+                .sum()
+        }
+
+        let parsed = parse_folders(&transcript_from_disk(&root).unwrap());
+        assert!(parsed.diagnostics().is_empty());
+        let tree = parsed.into_tree();
+        let du = tree.du();
+        let reported = tree
+            .iter()
+            .map(|(id, _)| disk_usage(&root.join(&tree.path(id)[1..])))
+            .collect::<Vec<usize>>();
+        fs::remove_dir_all(&root).unwrap_or_default();
+        assert_eq!(
+            tree.iter().map(|(id, _)| du[id]).collect::<Vec<usize>>(),
+            reported
+        );
+
+        let wide_size = (0..300).sum::<usize>();
+        assert_eq!(du[tree.lookup("/wide").unwrap()], wide_size);
+        assert_eq!(du[tree.lookup("/deep").unwrap()], 4096);
+        assert_eq!(du[tree.lookup("/empty dir").unwrap()], 0);
+        assert_eq!(tree.get_total_size(), wide_size + 4096 + 5);
+        let bottom = (0..40).fold(String::from("/deep"), |path, level| {
+            format!("{path}/l{level}")
+        });
+        assert_eq!(
+            tree.lookup(&format!("{bottom}/bottom.bin"))
+                .map(|id| du[id]),
+            Some(4096)
+        );
+    }
+
+    #[test]
+    fn test_diagnostics() {
+        let browsing_state = parse_folders(
+            "$ cd /
+$ ls
+dir a
+10 x
+$ ls
+10 x
+dir a
+12 a
+$ cd a
+$ ls
+5 y
+
+$ cd b
+$ cd /
+$ cd ..
+$ cd x
+$ mkdir c
+$ cd
+ls
+abc z
+$",
+        );
+        let diagnostics = browsing_state
+            .diagnostics()
+            .iter()
+            .map(|diagnostic| {
+                (
+                    diagnostic.line,
+                    diagnostic.is_error(),
+                    diagnostic.to_string(),
+                )
+            })
+            .collect::<Vec<(usize, bool, String)>>();
+        assert_eq!(
+            diagnostics,
+            [
+                (
+                    5,
+                    false,
+                    String::from("line 5: / was already listed, entries are deduplicated")
+                ),
+                (
+                    8,
+                    true,
+                    String::from("line 8: /a was listed before with another type or size")
+                ),
+                (
+                    13,
+                    false,
+                    String::from("line 13: cd into /a/b which was never listed")
+                ),
+                (15, false, String::from("line 15: cd .. from /")),
+                (16, true, String::from("line 16: cd into file /x")),
+                (17, true, String::from("line 17: unknown command `mkdir`")),
+                (18, true, String::from("line 18: malformed command `$ cd`")),
+                (19, true, String::from("line 19: output `ls` outside of ls")),
+                (
+                    20,
+                    true,
+                    String::from("line 20: output `abc z` outside of ls")
+                ),
+                (21, true, String::from("line 21: malformed command `$`")),
+            ]
+        );
+        assert!(browsing_state.has_errors());
+        assert_eq!(browsing_state.tree().get_total_size(), 15);
+        assert!(!parse_folders(INPUT).has_errors());
+        assert!(parse_folders(INPUT).diagnostics().is_empty());
+    }
 }
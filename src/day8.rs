@@ -1,196 +1,395 @@
-use std::{
-    collections::{HashMap, HashSet},
-    iter::Rev,
-    ops::Range,
-};
+use std::{fmt, str::FromStr};
 
 use aoc_runner_derive::{aoc, aoc_generator};
-use itertools::Either;
 
-#[aoc_generator(day8)]
-pub fn parse(
-    input: &str,
-) -> (
-    HashMap<(usize, usize), u32>,
-    (usize, usize),
-    HashMap<u32, Vec<(usize, usize)>>,
-) {
-    let mut sizes = (0, 0);
-    let mut grid: HashMap<(usize, usize), u32> = HashMap::new();
-    let mut trees_by_size: HashMap<u32, Vec<(usize, usize)>> = HashMap::new();
-    input.lines().enumerate().for_each(|(line_index, line)| {
-        line.chars().enumerate().for_each(|(col_index, c)| {
-            if line_index + 1 > sizes.0 {
-                sizes.0 = line_index + 1;
-            }
-            if col_index + 1 > sizes.1 {
-                sizes.1 = line_index + 1;
-            }
-            let tree_height = c.to_digit(10).expect("cannot parse");
-            let trees_of_height = trees_by_size.entry(tree_height).or_insert(vec![]);
-            trees_of_height.push((col_index, line_index));
-            grid.insert((col_index, line_index), tree_height);
-        })
-    });
-    (grid, sizes, trees_by_size)
+/// Dense row-major matrix, `(x, y)` being the column and the line of a cell.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Grid<T> {
+    width: usize,
+    height: usize,
+    cells: Vec<T>,
 }
 
-pub fn get_range(max: &usize, reverse: bool) -> Either<Range<usize>, Rev<Range<usize>>> {
-    let range = 0..*max;
-    if reverse {
-        Either::Right(range.rev())
-    } else {
-        Either::Left(range)
+impl<T: Clone> Grid<T> {
+    pub fn filled(width: usize, height: usize, value: T) -> Self {
+        Self {
+            width,
+            height,
+            cells: vec![value; width * height],
+        }
     }
 }
 
-pub fn browse_column(
-    line_index: usize,
-    column_size: &usize,
-    grid: &HashMap<(usize, usize), u32>,
-    reverse: bool,
-    mut visible_trees: HashSet<(usize, usize)>,
-) -> HashSet<(usize, usize)> {
-    let mut highest_for_col = -1;
-    for col_index in get_range(column_size, reverse) {
-        let curr_height = grid
-            .get(&(col_index, line_index))
-            .expect("tried to get outside of grid");
-        if *curr_height as i32 > highest_for_col {
-            highest_for_col = *curr_height as i32;
-            visible_trees.insert((col_index, line_index));
+impl<T> Grid<T> {
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Option<&T> {
+        if x >= self.width || y >= self.height {
+            return None;
         }
-        if curr_height == &9 {
-            break;
+        self.cells.get(y * self.width + x)
+    }
+
+    pub fn cells(&self) -> &[T] {
+        &self.cells
+    }
+
+    pub fn rows(&self) -> impl Iterator<Item = &[T]> {
+        self.cells.chunks(self.width.max(1))
+    }
+
+    /// Plain (ASCII) netpbm image of the grid, `header` being the magic number and
+    /// `pixel` turning a cell into its space separated samples.
+    fn to_netpbm(&self, header: &str, max_value: u8, pixel: impl Fn(&T) -> String) -> String {
+        let mut output = format!("{header}\n{} {}\n{max_value}\n", self.width, self.height);
+        for row in self.rows() {
+            let line = row.iter().map(&pixel).collect::<Vec<String>>().join(" ");
+            output.push_str(&line);
+            output.push('\n');
         }
+        output
     }
-    visible_trees
 }
 
-pub fn browse_line(
-    col_index: usize,
-    line_size: &usize,
-    grid: &HashMap<(usize, usize), u32>,
-    reverse: bool,
-    mut visible_trees: HashSet<(usize, usize)>,
-) -> HashSet<(usize, usize)> {
-    let mut highest_for_line = -1;
-    for line_index in get_range(line_size, reverse) {
-        let curr_height = grid
-            .get(&(col_index, line_index))
-            .expect("tried to get outside of grid");
-        if *curr_height as i32 > highest_for_line {
-            highest_for_line = *curr_height as i32;
-            visible_trees.insert((col_index, line_index));
-        }
-        if curr_height == &9 {
-            break;
-        }
+impl<T: fmt::Display> Grid<T> {
+    pub fn to_csv(&self) -> String {
+        self.rows()
+            .map(|row| {
+                row.iter()
+                    .map(|cell| cell.to_string())
+                    .collect::<Vec<String>>()
+                    .join(",")
+            })
+            .map(|line| line + "\n")
+            .collect()
     }
-    visible_trees
 }
 
-pub fn get_distance_from(base_tree: &(i32, i32), other_tree: &(i32, i32)) -> (usize, usize) {
-    match (base_tree.0 - other_tree.0, base_tree.1 - other_tree.1) {
-        (col_distance, line_distance) if col_distance > 0 && line_distance == 0 => {
-            (col_distance as usize, 3)
-        }
-        (col_distance, line_distance) if col_distance < 0 && line_distance == 0 => {
-            (-col_distance as usize, 1)
+impl Grid<bool> {
+    /// Grayscale image with white cells where the mask is set.
+    pub fn to_pgm(&self) -> String {
+        self.to_netpbm("P2", 255, |&set| if set { "255" } else { "0" }.to_string())
+    }
+}
+
+impl Grid<usize> {
+    fn normalized(&self) -> impl Fn(&usize) -> f64 {
+        let max = self.cells.iter().copied().max().unwrap_or(0).max(1) as f64;
+        move |&value| value as f64 / max
+    }
+
+    /// Grayscale image, the highest value of the grid being white.
+    pub fn to_pgm(&self) -> String {
+        let normalized = self.normalized();
+        self.to_netpbm("P2", 255, |value| {
+            ((normalized(value) * 255.0).round() as u8).to_string()
+        })
+    }
+
+    /// Color image going from black through red and yellow to white as values grow.
+    pub fn to_ppm(&self) -> String {
+        let normalized = self.normalized();
+        self.to_netpbm("P3", 255, |value| {
+            let heat = normalized(value) * 3.0;
+            let channel = |offset: f64| ((heat - offset).clamp(0.0, 1.0) * 255.0).round() as u8;
+            format!("{} {} {}", channel(0.0), channel(1.0), channel(2.0))
+        })
+    }
+}
+
+pub type Forest = Grid<u8>;
+
+impl Forest {
+    /// Every line of sight from an edge of the forest, as indices into the cells.
+    fn lines_of_sight(&self) -> Vec<Vec<usize>> {
+        let (width, height) = (self.width, self.height);
+        let mut lines = vec![];
+        for y in 0..height {
+            let row = (0..width).map(|x| y * width + x).collect::<Vec<usize>>();
+            lines.push(row.iter().rev().copied().collect());
+            lines.push(row);
         }
-        (col_distance, line_distance) if col_distance == 0 && line_distance > 0 => {
-            (line_distance as usize, 0)
+        for x in 0..width {
+            let column = (0..height).map(|y| y * width + x).collect::<Vec<usize>>();
+            lines.push(column.iter().rev().copied().collect());
+            lines.push(column);
         }
-        (col_distance, line_distance) if col_distance == 0 && line_distance < 0 => {
-            (-line_distance as usize, 2)
+        lines
+    }
+
+    /// Computes visibility from the edges and scenic scores of every tree in one sweep
+    /// per line of sight, keeping a stack of the trees not yet hidden by a taller one.
+    pub fn survey(&self) -> (Grid<bool>, Grid<usize>) {
+        let mut visible = Grid::filled(self.width, self.height, false);
+        let mut scores = Grid::filled(self.width, self.height, 1);
+        let mut stack: Vec<(usize, u8)> = vec![];
+        for line in self.lines_of_sight() {
+            stack.clear();
+            for (position, &index) in line.iter().enumerate() {
+                let height = self.cells[index];
+                while stack.last().is_some_and(|&(_, other)| other < height) {
+                    stack.pop();
+                }
+                match stack.last() {
+                    Some(&(blocking, _)) => scores.cells[index] *= position - blocking,
+                    None => {
+                        visible.cells[index] = true;
+                        scores.cells[index] *= position;
+                    }
+                }
+                stack.push((position, height));
+            }
         }
-        _ => unreachable!("comapred to self ??"),
+        (visible, scores)
+    }
+
+    pub fn visibility(&self) -> Grid<bool> {
+        self.survey().0
+    }
+
+    pub fn scenic_scores(&self) -> Grid<usize> {
+        self.survey().1
+    }
+
+    /// Coordinates of the tree with the highest scenic score, the first one in reading
+    /// order on ties.
+    pub fn best_tree(&self) -> Option<(usize, usize)> {
+        let scores = self.scenic_scores();
+        let (index, _) = scores
+            .cells
+            .iter()
+            .enumerate()
+            .rev()
+            .max_by_key(|(_, &score)| score)?;
+        Some((index % self.width, index / self.width))
     }
 }
 
-#[aoc(day8, part1)]
-pub fn solve_part1(
-    (grid, (col_size, line_size), _): &(
-        HashMap<(usize, usize), u32>,
-        (usize, usize),
-        HashMap<u32, Vec<(usize, usize)>>,
-    ),
-) -> usize {
-    let mut visible_trees: HashSet<(usize, usize)> = HashSet::new();
-    // Browse by column
-    for i in 0..*line_size {
-        visible_trees = browse_column(i, &col_size, &grid, false, visible_trees);
-        visible_trees = browse_column(i, &col_size, &grid, true, visible_trees);
-    }
-
-    for i in 0..*col_size {
-        visible_trees = browse_line(i, &line_size, &grid, false, visible_trees);
-        visible_trees = browse_line(i, &line_size, &grid, true, visible_trees);
-    }
-
-    visible_trees.iter().count()
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ForestError {
+    RaggedLine {
+        line: usize,
+        expected: usize,
+        found: usize,
+    },
+    InvalidHeight {
+        line: usize,
+        column: usize,
+        found: char,
+    },
 }
 
-#[aoc(day8, part2)]
-pub fn solve_part2(
-    (grid, (col_size, line_size), trees_by_size): &(
-        HashMap<(usize, usize), u32>,
-        (usize, usize),
-        HashMap<u32, Vec<(usize, usize)>>,
-    ),
-) -> usize {
-    let mut max_score = 0;
-    for tested_height in (5..10).rev() {
-        let default_vec: Vec<(usize, usize)> = vec![];
-        let trees_of_size = match trees_by_size.get(&tested_height) {
-            Some(vector) => vector,
-            None => &default_vec,
-        };
-        // Top Right Bottom Left
-        for tree in trees_of_size {
-            if tree.0 == 0 || tree.1 == 0 || tree.0 == line_size - 1 || tree.1 == col_size - 1 {
-                continue;
-            }
+impl fmt::Display for ForestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ForestError::RaggedLine {
+                line,
+                expected,
+                found,
+            } => write!(f, "line {line} has {found} trees, expected {expected}"),
+            ForestError::InvalidHeight {
+                line,
+                column,
+                found,
+            } => write!(
+                f,
+                "line {line}, column {column}: `{found}` is not a tree height"
+            ),
+        }
+    }
+}
 
-            // set distance to closest by using forest boundaries as a default value
-            let mut distance_to_closest: Vec<(usize, usize)> = vec![
-                (tree.1, *(grid.get(&(tree.0, 0)).unwrap()) as usize),
-                (
-                    line_size - 1 - tree.0,
-                    *(grid.get(&(tree.1, line_size - 1)).unwrap()) as usize,
-                ),
-                (
-                    col_size - 1 - tree.1,
-                    *(grid.get(&(col_size - 1, tree.0)).unwrap()) as usize,
-                ),
-                (tree.0, *(grid.get(&(tree.1, 0)).unwrap()) as usize),
-            ];
-            for other_height in tested_height..10 {
-                let trees_of_other_size = match trees_by_size.get(&other_height) {
-                    Some(vector) => vector,
-                    None => &default_vec,
-                };
-                for other_tree in trees_of_other_size {
-                    if (other_tree.0 != tree.0 && other_tree.1 != tree.1) || other_tree == tree {
-                        continue;
-                    }
+impl std::error::Error for ForestError {}
 
-                    let (dist, index) = get_distance_from(
-                        &(tree.0 as i32, tree.1 as i32),
-                        &(other_tree.0 as i32, other_tree.1 as i32),
-                    );
+impl FromStr for Forest {
+    type Err = ForestError;
 
-                    let closest = distance_to_closest[index];
-                    if closest.0 > dist {
-                        distance_to_closest[index] = (dist, other_height as usize);
-                    }
-                }
+    /// Reads a rectangular forest, every line of the input being a line of trees.
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let lines = input.lines().map(str::trim_end).collect::<Vec<&str>>();
+        let width = lines.first().map_or(0, |line| line.chars().count());
+        let mut cells = Vec::with_capacity(width * lines.len());
+        for (line_index, line) in lines.iter().enumerate() {
+            let length = line.chars().count();
+            if length != width {
+                return Err(ForestError::RaggedLine {
+                    line: line_index + 1,
+                    expected: width,
+                    found: length,
+                });
             }
-            let score: usize = distance_to_closest.iter().map(|dist| dist.0).product();
-            if score > max_score {
-                max_score = score;
+            for (col_index, c) in line.chars().enumerate() {
+                let height = c.to_digit(10).ok_or(ForestError::InvalidHeight {
+                    line: line_index + 1,
+                    column: col_index + 1,
+                    found: c,
+                })?;
+                cells.push(height as u8);
             }
         }
+        Ok(Forest {
+            width,
+            height: lines.len(),
+            cells,
+        })
+    }
+}
+
+#[aoc_generator(day8)]
+pub fn parse(input: &str) -> Forest {
+    input
+        .parse()
+        .unwrap_or_else(|err| panic!("invalid forest: {err}"))
+}
+
+#[aoc(day8, part1)]
+pub fn solve_part1(forest: &Forest) -> usize {
+    forest
+        .visibility()
+        .cells()
+        .iter()
+        .filter(|&&visible| visible)
+        .count()
+}
+
+#[aoc(day8, part2)]
+pub fn solve_part2(forest: &Forest) -> usize {
+    forest
+        .scenic_scores()
+        .cells()
+        .iter()
+        .copied()
+        .max()
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::day8::*;
+
+    const INPUT: &str = "30373
+25512
+65332
+33549
+35390";
+
+    #[test]
+    fn test_p1() {
+        assert_eq!(solve_part1(&parse(INPUT)), 21);
+    }
+
+    #[test]
+    fn test_p2() {
+        assert_eq!(solve_part2(&parse(INPUT)), 8);
+    }
+
+    #[test]
+    fn test_survey() {
+        let (visible, scores) = parse(INPUT).survey();
+        assert_eq!(
+            visible.rows().collect::<Vec<&[bool]>>(),
+            [
+                [true, true, true, true, true],
+                [true, true, true, false, true],
+                [true, true, false, true, true],
+                [true, false, true, false, true],
+                [true, true, true, true, true],
+            ]
+        );
+        assert_eq!(scores.get(2, 1), Some(&4));
+        assert_eq!(scores.get(2, 3), Some(&8));
+        assert_eq!(scores.get(0, 2), Some(&0));
+    }
+
+    #[test]
+    fn test_exports() {
+        let forest = parse(INPUT);
+        assert_eq!(forest.best_tree(), Some((2, 3)));
+        assert_eq!(parse("").best_tree(), None);
+
+        let (visible, scores) = forest.survey();
+        assert_eq!(
+            scores.to_csv(),
+            "0,0,0,0,0
+0,1,4,1,0
+0,6,1,2,0
+0,1,8,3,0
+0,0,0,0,0
+"
+        );
+        assert_eq!(
+            visible.to_pgm(),
+            "P2
+5 5
+255
+255 255 255 255 255
+255 255 255 0 255
+255 255 0 255 255
+255 0 255 0 255
+255 255 255 255 255
+"
+        );
+        assert_eq!(scores.to_pgm().lines().nth(6), Some("0 32 255 96 0"));
+        assert_eq!(
+            scores.to_ppm().lines().nth(6),
+            Some("0 0 0 96 0 0 255 255 255 255 32 0 0 0 0")
+        );
+    }
+
+    #[test]
+    fn test_single_line() {
+        let forest = parse("30373");
+        assert_eq!((forest.width(), forest.height()), (5, 1));
+        assert_eq!(solve_part1(&forest), 5);
+        assert_eq!(solve_part2(&forest), 0);
+    }
+
+    #[test]
+    fn test_single_column() {
+        let forest = parse("3\n0\n3\n7\n3");
+        assert_eq!((forest.width(), forest.height()), (1, 5));
+        assert_eq!(solve_part1(&forest), 5);
+        assert_eq!(solve_part2(&forest), 0);
+    }
+
+    #[test]
+    fn test_rectangular() {
+        let wide = parse("30373\n25512\n65332");
+        assert_eq!((wide.width(), wide.height()), (5, 3));
+        assert_eq!(solve_part1(&wide), 14);
+        assert_eq!(solve_part2(&wide), 2);
+        assert_eq!(wide.scenic_scores().get(2, 1), Some(&2));
+
+        let tall = parse("326\n056\n353\n713\n322");
+        assert_eq!((tall.width(), tall.height()), (3, 5));
+        assert_eq!(solve_part1(&tall), 14);
+        assert_eq!(solve_part2(&tall), 2);
+        assert_eq!(tall.scenic_scores().get(1, 2), Some(&2));
+    }
+
+    #[test]
+    fn test_invalid_forests() {
+        assert_eq!(
+            "303\n2551\n653".parse::<Forest>(),
+            Err(ForestError::RaggedLine {
+                line: 2,
+                expected: 3,
+                found: 4
+            })
+        );
+        assert_eq!(
+            "303\n2a5".parse::<Forest>(),
+            Err(ForestError::InvalidHeight {
+                line: 2,
+                column: 2,
+                found: 'a'
+            })
+        );
     }
-    max_score
 }
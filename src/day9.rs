@@ -1,9 +1,8 @@
 use std::{
-    cell::RefCell,
     collections::HashSet,
+    fmt,
     hash::Hash,
     ops::{Add, Sub},
-    rc::Rc,
 };
 
 use aoc_runner_derive::{aoc, aoc_generator};
@@ -12,27 +11,81 @@ use aoc_runner_derive::{aoc, aoc_generator};
 pub fn parse(input: &str) -> Vec<Move> {
     input.lines().map(|line| line.into()).collect::<Vec<Move>>()
 }
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
-pub struct Coordinate(i32, i32);
 
-impl Coordinate {
-    pub fn is_adjacent_with(&self, other: &Self) -> bool {
-        (self.0 - other.0).abs() <= 1 && (self.1 - other.1).abs() <= 1
+/// Point knots can move to, whatever the number of dimensions of the space.
+pub trait Position: Copy + Eq + Hash {
+    fn offset(&self, vector: &Self) -> Self;
+
+    fn chebyshev_distance(&self, other: &Self) -> u32;
+
+    /// Moves by at most one unit on every axis towards `other`.
+    fn step_towards(&self, other: &Self) -> Self;
+
+    /// Position reached when following `leader`: a knot only moves once the leader is
+    /// more than `slack` away.
+    fn follow(&self, leader: &Self, slack: u32) -> Self {
+        if self.chebyshev_distance(leader) <= slack {
+            *self
+        } else {
+            self.step_towards(leader)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Coordinate(pub i32, pub i32);
+
+impl Position for Coordinate {
+    fn offset(&self, vector: &Self) -> Self {
+        self + vector
+    }
+
+    fn chebyshev_distance(&self, other: &Self) -> u32 {
+        self.0.abs_diff(other.0).max(self.1.abs_diff(other.1))
+    }
+
+    fn step_towards(&self, other: &Self) -> Self {
+        let vector = other - self;
+        Coordinate(self.0 + vector.0.signum(), self.1 + vector.1.signum())
     }
 }
 
-impl<'a, 'b> Add<&'b Coordinate> for &'a Coordinate {
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Coordinate3(pub i32, pub i32, pub i32);
+
+impl Position for Coordinate3 {
+    fn offset(&self, vector: &Self) -> Self {
+        Coordinate3(self.0 + vector.0, self.1 + vector.1, self.2 + vector.2)
+    }
+
+    fn chebyshev_distance(&self, other: &Self) -> u32 {
+        self.0
+            .abs_diff(other.0)
+            .max(self.1.abs_diff(other.1))
+            .max(self.2.abs_diff(other.2))
+    }
+
+    fn step_towards(&self, other: &Self) -> Self {
+        Coordinate3(
+            self.0 + (other.0 - self.0).signum(),
+            self.1 + (other.1 - self.1).signum(),
+            self.2 + (other.2 - self.2).signum(),
+        )
+    }
+}
+
+impl Add<&Coordinate> for &Coordinate {
     type Output = Coordinate;
 
-    fn add(self, other: &'b Coordinate) -> Coordinate {
+    fn add(self, other: &Coordinate) -> Coordinate {
         Coordinate(self.0 + other.0, self.1 + other.1)
     }
 }
 
-impl<'a, 'b> Sub<&'b Coordinate> for &'a Coordinate {
+impl Sub<&Coordinate> for &Coordinate {
     type Output = Coordinate;
 
-    fn sub(self, other: &'b Coordinate) -> Coordinate {
+    fn sub(self, other: &Coordinate) -> Coordinate {
         Coordinate(self.0 - other.0, self.1 - other.1)
     }
 }
@@ -60,6 +113,18 @@ pub struct Move {
     amount: usize,
 }
 
+impl fmt::Display for Move {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let direction = match self.direction {
+            Direction::Top => 'U',
+            Direction::Right => 'R',
+            Direction::Bottom => 'D',
+            Direction::Left => 'L',
+        };
+        write!(f, "{direction} {}", self.amount)
+    }
+}
+
 impl Move {
     pub fn iter_vector(&self) -> impl Iterator<Item = Coordinate> + '_ {
         (0..self.amount).map(|_| self.direction.get_vector())
@@ -82,97 +147,447 @@ impl From<&str> for Move {
     }
 }
 
-#[derive(Clone)]
-pub struct Knot {
-    position: Coordinate,
-    tail: Option<Rc<RefCell<Knot>>>,
-    followed_positions: HashSet<Coordinate>,
+/// Rope made of `knots`, the first one being the head, and the positions visited by the
+/// knots we are interested in.
+#[derive(Debug, Clone)]
+pub struct Rope<P = Coordinate> {
+    knots: Vec<P>,
+    visited: Vec<Option<HashSet<P>>>,
+    slack: u32,
 }
 
-impl Knot {
-    pub fn new(size: usize) -> Self {
-        let mut new_knot = Self {
-            position: Coordinate(0, 0),
-            followed_positions: HashSet::<Coordinate>::new(),
-            tail: None,
-        };
-
-        if size == 0 {
-            return new_knot;
-        }
-        new_knot.tail = Some(Rc::new(RefCell::new(Knot::new(size - 1))));
-        return new_knot;
+impl Rope {
+    pub fn new(length: usize) -> Self {
+        Self::at(START, length)
     }
 
     pub fn move_with(&mut self, mv: &Move) {
         for vector in mv.iter_vector() {
-            self.position = &self.position + &vector;
-            match &self.tail {
-                Some(t) => t.borrow_mut().follow(&self.position),
-                None => {}
-            };
+            self.step(&vector);
         }
     }
+}
 
-    pub fn follow(&mut self, position: &Coordinate) {
-        if self.position.is_adjacent_with(&position) {
-            return;
+impl<P: Position> Rope<P> {
+    /// Rope of `length` knots all lying on `origin`.
+    pub fn at(origin: P, length: usize) -> Self {
+        assert!(length > 0, "a rope needs at least one knot");
+        Self {
+            knots: vec![origin; length],
+            visited: vec![None; length],
+            slack: 1,
         }
+    }
 
-        let vec = position - &self.position;
-        match vec {
-            Coordinate(0, y) => {
-                self.position = &self.position + &Coordinate(0, if y > 0 { y - 1 } else { y + 1 })
-            }
-            Coordinate(x, 0) => {
-                self.position = &self.position + &Coordinate(if x > 0 { x - 1 } else { x + 1 }, 0)
-            }
-            Coordinate(x, y) if y.abs() == 1 => {
-                self.position = &self.position + &Coordinate(if x > 0 { x - 1 } else { x + 1 }, y)
-            }
-            Coordinate(x, y) if x.abs() == 1 => {
-                self.position = &self.position + &Coordinate(x, if y > 0 { y - 1 } else { y + 1 })
-            }
-            Coordinate(x, y) if x.abs() == 2 && y.abs() == 2 => {
-                self.position = &self.position
-                    + &Coordinate(
-                        if x > 0 { x - 1 } else { x + 1 },
-                        if y > 0 { y - 1 } else { y + 1 },
-                    )
-            }
-            _ => unreachable!("Found unmatched vector while following : {:#?}", vec),
-        };
+    /// Distance a knot can be from the previous one before having to move, 1 by default.
+    pub fn with_slack(mut self, slack: u32) -> Self {
+        self.slack = slack;
+        self
+    }
+
+    /// Starts recording the positions visited by the knot at `index`, 0 being the head.
+    pub fn track(&mut self, index: usize) {
+        self.visited[index].get_or_insert_with(|| HashSet::from([self.knots[index]]));
+    }
+
+    pub fn track_all(&mut self) {
+        for index in 0..self.knots.len() {
+            self.track(index);
+        }
+    }
+
+    pub fn knots(&self) -> &[P] {
+        &self.knots
+    }
+
+    pub fn head(&self) -> &P {
+        &self.knots[0]
+    }
 
-        match &self.tail {
-            Some(t) => t.borrow_mut().follow(&self.position),
-            None => {
-                self.followed_positions.insert(self.position.clone());
+    pub fn tail(&self) -> &P {
+        &self.knots[self.knots.len() - 1]
+    }
+
+    pub fn tail_index(&self) -> usize {
+        self.knots.len() - 1
+    }
+
+    /// Positions visited by the knot at `index`, if it is tracked.
+    pub fn visited(&self, index: usize) -> Option<&HashSet<P>> {
+        self.visited.get(index)?.as_ref()
+    }
+
+    pub fn step(&mut self, vector: &P) {
+        self.knots[0] = self.knots[0].offset(vector);
+        self.record(0);
+        for index in 1..self.knots.len() {
+            let position = self.knots[index].follow(&self.knots[index - 1], self.slack);
+            // Once a knot stays still, none of the following ones can move either
+            if position == self.knots[index] {
+                break;
             }
+            self.knots[index] = position;
+            self.record(index);
         }
     }
 
-    pub fn get_tail(&self) -> Self {
-        match &self.tail {
-            Some(t) => t.borrow().get_tail().clone(),
-            None => self.clone(),
+    fn record(&mut self, index: usize) {
+        if let Some(visited) = &mut self.visited[index] {
+            visited.insert(self.knots[index]);
         }
     }
 }
 
-#[aoc(day9, part1)]
-pub fn solve_part1(moves: &Vec<Move>) -> usize {
-    let mut head = Knot::new(1);
+impl fmt::Display for Rope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let renderer = Renderer::fitting(self.knots.iter().chain([&START]));
+        write!(f, "{}", renderer.render_knots(&self.knots))
+    }
+}
+
+/// State of the rope after a single step of one of the moves.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    pub move_index: usize,
+    pub step: usize,
+    pub knots: Vec<Coordinate>,
+}
+
+/// Iterator applying moves to a rope one step at a time.
+pub struct Replay<'a> {
+    rope: Rope,
+    moves: &'a [Move],
+    move_index: usize,
+    step: usize,
+}
+
+impl<'a> Replay<'a> {
+    pub fn new(rope: Rope, moves: &'a [Move]) -> Self {
+        Self {
+            rope,
+            moves,
+            move_index: 0,
+            step: 0,
+        }
+    }
+
+    pub fn rope(&self) -> &Rope {
+        &self.rope
+    }
+}
+
+impl Iterator for Replay<'_> {
+    type Item = Frame;
+
+    fn next(&mut self) -> Option<Frame> {
+        while self.moves.get(self.move_index)?.amount == self.step {
+            self.move_index += 1;
+            self.step = 0;
+        }
+        let mv = &self.moves[self.move_index];
+        self.rope.step(&mv.direction.get_vector());
+        self.step += 1;
+        Some(Frame {
+            move_index: self.move_index,
+            step: self.step,
+            knots: self.rope.knots.clone(),
+        })
+    }
+}
+
+const START: Coordinate = Coordinate(0, 0);
+
+/// Draws rope states inside a fixed bounding box, the same way the puzzle does.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Renderer {
+    min: Coordinate,
+    max: Coordinate,
+}
+
+impl Renderer {
+    pub fn new(min: Coordinate, max: Coordinate) -> Self {
+        Self { min, max }
+    }
+
+    /// Smallest bounding box containing all the given positions.
+    pub fn fitting<'a>(positions: impl IntoIterator<Item = &'a Coordinate>) -> Self {
+        let mut renderer = Self::new(START, START);
+        for position in positions {
+            renderer.min = Coordinate(
+                renderer.min.0.min(position.0),
+                renderer.min.1.min(position.1),
+            );
+            renderer.max = Coordinate(
+                renderer.max.0.max(position.0),
+                renderer.max.1.max(position.1),
+            );
+        }
+        renderer
+    }
+
+    /// Bounding box of every position any knot goes through while replaying `moves`.
+    pub fn fitting_moves(moves: &[Move], length: usize) -> Self {
+        let mut renderer = Self::new(START, START);
+        for frame in Replay::new(Rope::new(length), moves) {
+            renderer = Self::fitting(frame.knots.iter().chain([&renderer.min, &renderer.max]));
+        }
+        renderer
+    }
+
+    fn render(&self, symbol_at: impl Fn(&Coordinate) -> Option<char>) -> String {
+        (self.min.1..=self.max.1)
+            .rev()
+            .map(|y| {
+                (self.min.0..=self.max.0)
+                    .map(|x| symbol_at(&Coordinate(x, y)).unwrap_or('.'))
+                    .collect::<String>()
+            })
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    /// Draws the knots, the head on top, and the starting position when nothing covers it.
+    pub fn render_knots(&self, knots: &[Coordinate]) -> String {
+        self.render(|position| {
+            knots
+                .iter()
+                .position(|knot| knot == position)
+                .map(|index| knot_symbol(index, knots.len()))
+                .or((position == &START).then_some('s'))
+        })
+    }
+
+    /// Draws the visited positions, marking the starting one with `s`.
+    pub fn render_visited(&self, visited: &HashSet<Coordinate>) -> String {
+        self.render(|position| {
+            if position == &START {
+                Some('s')
+            } else {
+                visited.contains(position).then_some('#')
+            }
+        })
+    }
+}
+
+fn knot_symbol(index: usize, length: usize) -> char {
+    match index {
+        0 => 'H',
+        i if i == length - 1 && (length == 2 || length > 10) => 'T',
+        i if i <= 9 => char::from_digit(i as u32, 10).unwrap(),
+        _ => '*',
+    }
+}
+
+pub fn count_tail_positions(moves: &[Move], length: usize) -> usize {
+    let mut rope = Rope::new(length);
+    rope.track(rope.tail_index());
     for mv in moves {
-        head.move_with(mv);
+        rope.move_with(mv);
     }
-    head.get_tail().followed_positions.len()
+    rope.visited(rope.tail_index())
+        .map_or(0, |visited| visited.len())
+}
+
+#[aoc(day9, part1)]
+pub fn solve_part1(moves: &[Move]) -> usize {
+    count_tail_positions(moves, 2)
 }
 
 #[aoc(day9, part2)]
-pub fn solve_part2(moves: &Vec<Move>) -> usize {
-    let mut head = Knot::new(9);
-    for mv in moves {
-        head.move_with(mv);
+pub fn solve_part2(moves: &[Move]) -> usize {
+    count_tail_positions(moves, 10)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::day9::*;
+
+    const INPUT: &str = "R 4
+U 4
+L 3
+D 1
+R 4
+D 1
+L 5
+R 2";
+
+    const LARGER_INPUT: &str = "R 5
+U 8
+L 8
+D 3
+R 17
+D 10
+L 25
+U 20";
+
+    #[test]
+    fn test_p1() {
+        assert_eq!(solve_part1(&parse(INPUT)), 13);
+    }
+
+    #[test]
+    fn test_p2() {
+        assert_eq!(solve_part2(&parse(INPUT)), 1);
+        assert_eq!(solve_part2(&parse(LARGER_INPUT)), 36);
+    }
+
+    #[test]
+    fn test_track_all() {
+        let mut rope = Rope::new(10);
+        rope.track_all();
+        for mv in parse(INPUT) {
+            rope.move_with(&mv);
+        }
+        assert_eq!(rope.visited(1).unwrap().len(), 13);
+        assert_eq!(rope.visited(9).unwrap().len(), 1);
+        assert_eq!(rope.head(), &Coordinate(2, 2));
+        assert_eq!(rope.tail(), &Coordinate(0, 0));
+    }
+
+    #[test]
+    fn test_slack() {
+        let mut rope = Rope::new(3).with_slack(2);
+        rope.track_all();
+        for mv in parse("R 5\nU 3") {
+            rope.move_with(&mv);
+        }
+        assert_eq!(
+            rope.knots(),
+            &[Coordinate(5, 3), Coordinate(4, 1), Coordinate(2, 1)]
+        );
+        assert_eq!(rope.visited(2).unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_3d_rope() {
+        let mut rope = Rope::at(Coordinate3(0, 0, 0), 3);
+        rope.track(2);
+        for _ in 0..3 {
+            rope.step(&Coordinate3(0, 0, 1));
+        }
+        assert_eq!(
+            rope.knots(),
+            &[
+                Coordinate3(0, 0, 3),
+                Coordinate3(0, 0, 2),
+                Coordinate3(0, 0, 1)
+            ]
+        );
+
+        rope.step(&Coordinate3(1, 1, 0));
+        rope.step(&Coordinate3(1, 0, 0));
+        assert_eq!(
+            rope.knots(),
+            &[
+                Coordinate3(2, 1, 3),
+                Coordinate3(1, 1, 3),
+                Coordinate3(1, 1, 2)
+            ]
+        );
+        assert_eq!(rope.visited(2).unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_replay_frames() {
+        let moves = parse(INPUT);
+        let renderer = Renderer::new(Coordinate(0, 0), Coordinate(5, 4));
+        let frames = Replay::new(Rope::new(2), &moves)
+            .take_while(|frame| frame.move_index == 0)
+            .map(|frame| renderer.render_knots(&frame.knots))
+            .collect::<Vec<String>>();
+        assert_eq!(moves[0].to_string(), "R 4");
+        assert_eq!(
+            frames,
+            [
+                "......\n......\n......\n......\nTH....",
+                "......\n......\n......\n......\nsTH...",
+                "......\n......\n......\n......\ns.TH..",
+                "......\n......\n......\n......\ns..TH.",
+            ]
+        );
+
+        let mut rope = Rope::new(2);
+        rope.track(1);
+        let mut replay = Replay::new(rope, &moves);
+        let last_frame = replay.by_ref().last().unwrap();
+        assert_eq!(
+            renderer.render_knots(&last_frame.knots),
+            "......
+......
+.TH...
+......
+s....."
+        );
+        assert_eq!(
+            renderer.render_visited(replay.rope().visited(1).unwrap()),
+            "..##..
+...##.
+.####.
+....#.
+s###.."
+        );
+    }
+
+    #[test]
+    fn test_larger_replay() {
+        let moves = parse(LARGER_INPUT);
+        let renderer = Renderer::fitting_moves(&moves, 10);
+        assert_eq!(
+            renderer,
+            Renderer::new(Coordinate(-11, -5), Coordinate(14, 15))
+        );
+
+        let mut rope = Rope::new(10);
+        rope.track(9);
+        let mut replay = Replay::new(rope, &moves);
+        let last_frame = replay.by_ref().last().unwrap();
+        assert_eq!(
+            renderer.render_knots(&last_frame.knots),
+            "H.........................
+1.........................
+2.........................
+3.........................
+4.........................
+5.........................
+6.........................
+7.........................
+8.........................
+9.........................
+..........................
+..........................
+..........................
+..........................
+..........................
+...........s..............
+..........................
+..........................
+..........................
+..........................
+.........................."
+        );
+        assert_eq!(
+            renderer.render_visited(replay.rope().visited(9).unwrap()),
+            "..........................
+..........................
+..........................
+..........................
+..........................
+..........................
+..........................
+..........................
+..........................
+#.........................
+#.............###.........
+#............#...#........
+.#..........#.....#.......
+..#..........#.....#......
+...#........#.......#.....
+....#......s.........#....
+.....#..............#.....
+......#............#......
+.......#..........#.......
+........#........#........
+.........########........."
+        );
     }
-    head.get_tail().followed_positions.len()
 }
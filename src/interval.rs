@@ -0,0 +1,289 @@
+use std::fmt;
+
+use num::PrimInt;
+
+/// Inclusive range of integers, both bounds being part of it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Interval<T> {
+    start: T,
+    end: T,
+}
+
+impl<T: PrimInt> Interval<T> {
+    /// Panics if `start` is after `end`, as the interval would be empty.
+    pub fn new(start: T, end: T) -> Self {
+        assert!(start <= end, "interval start is after its end");
+        Self { start, end }
+    }
+
+    pub fn single(value: T) -> Self {
+        Self::new(value, value)
+    }
+
+    pub fn start(&self) -> T {
+        self.start
+    }
+
+    pub fn end(&self) -> T {
+        self.end
+    }
+
+    /// Number of values in the interval, which overflows for the full range of `T`.
+    pub fn len(&self) -> T {
+        self.end - self.start + T::one()
+    }
+
+    pub fn contains(&self, value: T) -> bool {
+        self.start <= value && value <= self.end
+    }
+
+    pub fn contains_interval(&self, other: &Self) -> bool {
+        self.start <= other.start && other.end <= self.end
+    }
+
+    pub fn overlaps(&self, other: &Self) -> bool {
+        self.start <= other.end && other.start <= self.end
+    }
+
+    /// Whether the intervals overlap or follow each other, their union being an interval.
+    pub fn touches(&self, other: &Self) -> bool {
+        self.start <= other.end.saturating_add(T::one())
+            && other.start <= self.end.saturating_add(T::one())
+    }
+
+    pub fn intersection(&self, other: &Self) -> Option<Self> {
+        if !self.overlaps(other) {
+            return None;
+        }
+        Some(Self::new(
+            self.start.max(other.start),
+            self.end.min(other.end),
+        ))
+    }
+
+    pub fn union(&self, other: &Self) -> IntervalSet<T> {
+        [*self, *other].into_iter().collect()
+    }
+
+    pub fn difference(&self, other: &Self) -> IntervalSet<T> {
+        let mut set = IntervalSet::from(*self);
+        set.remove(other);
+        set
+    }
+}
+
+impl<T: fmt::Display> fmt::Display for Interval<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}", self.start, self.end)
+    }
+}
+
+/// Union of intervals, kept sorted with neither overlapping nor adjacent intervals.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct IntervalSet<T> {
+    intervals: Vec<Interval<T>>,
+}
+
+impl<T> Default for IntervalSet<T> {
+    fn default() -> Self {
+        Self { intervals: vec![] }
+    }
+}
+
+impl<T: PrimInt> IntervalSet<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn intervals(&self) -> &[Interval<T>] {
+        &self.intervals
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.intervals.is_empty()
+    }
+
+    /// Number of values in the set.
+    pub fn covered(&self) -> T {
+        self.intervals
+            .iter()
+            .fold(T::zero(), |covered, interval| covered + interval.len())
+    }
+
+    pub fn contains(&self, value: T) -> bool {
+        let index = self
+            .intervals
+            .partition_point(|interval| interval.end < value);
+        self.intervals
+            .get(index)
+            .is_some_and(|interval| interval.contains(value))
+    }
+
+    /// Adds an interval, merging it with the intervals it overlaps or touches.
+    pub fn insert(&mut self, interval: Interval<T>) {
+        let first = self
+            .intervals
+            .partition_point(|other| other.end.saturating_add(T::one()) < interval.start);
+        let last = self
+            .intervals
+            .partition_point(|other| other.start <= interval.end.saturating_add(T::one()));
+        let merged = if first < last {
+            Interval::new(
+                interval.start.min(self.intervals[first].start),
+                interval.end.max(self.intervals[last - 1].end),
+            )
+        } else {
+            interval
+        };
+        self.intervals.splice(first..last, [merged]);
+    }
+
+    /// Takes the values of an interval out of the set, splitting the intervals it is within.
+    pub fn remove(&mut self, interval: &Interval<T>) {
+        let first = self
+            .intervals
+            .partition_point(|other| other.end < interval.start);
+        let last = self
+            .intervals
+            .partition_point(|other| other.start <= interval.end);
+        if first == last {
+            return;
+        }
+        let (lowest, highest) = (self.intervals[first], self.intervals[last - 1]);
+        let mut kept = vec![];
+        if lowest.start < interval.start {
+            kept.push(Interval::new(lowest.start, interval.start - T::one()));
+        }
+        if interval.end < highest.end {
+            kept.push(Interval::new(interval.end + T::one(), highest.end));
+        }
+        self.intervals.splice(first..last, kept);
+    }
+
+    pub fn union(&self, other: &Self) -> Self {
+        let mut union = self.clone();
+        other
+            .intervals
+            .iter()
+            .for_each(|&interval| union.insert(interval));
+        union
+    }
+
+    pub fn intersection(&self, other: &Self) -> Self {
+        let (mut left, mut right) = (self.intervals.iter(), other.intervals.iter());
+        let (mut a, mut b) = (left.next(), right.next());
+        let mut intervals = vec![];
+        while let (Some(first), Some(second)) = (a, b) {
+            intervals.extend(first.intersection(second));
+            // The interval ending first cannot meet any further interval of the other set
+            if first.end < second.end {
+                a = left.next();
+            } else {
+                b = right.next();
+            }
+        }
+        Self { intervals }
+    }
+
+    pub fn difference(&self, other: &Self) -> Self {
+        let mut difference = self.clone();
+        other
+            .intervals
+            .iter()
+            .for_each(|interval| difference.remove(interval));
+        difference
+    }
+}
+
+impl<T: PrimInt> From<Interval<T>> for IntervalSet<T> {
+    fn from(interval: Interval<T>) -> Self {
+        Self {
+            intervals: vec![interval],
+        }
+    }
+}
+
+impl<T: PrimInt> FromIterator<Interval<T>> for IntervalSet<T> {
+    fn from_iter<I: IntoIterator<Item = Interval<T>>>(intervals: I) -> Self {
+        let mut set = Self::new();
+        intervals
+            .into_iter()
+            .for_each(|interval| set.insert(interval));
+        set
+    }
+}
+
+impl<T: fmt::Display> fmt::Display for IntervalSet<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let intervals = self
+            .intervals
+            .iter()
+            .map(|interval| interval.to_string())
+            .collect::<Vec<String>>();
+        write!(f, "{}", intervals.join(","))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::interval::*;
+
+    fn set(intervals: &[(i32, i32)]) -> IntervalSet<i32> {
+        intervals
+            .iter()
+            .map(|&(start, end)| Interval::new(start, end))
+            .collect()
+    }
+
+    #[test]
+    fn test_interval() {
+        let (a, b) = (Interval::new(2, 8), Interval::new(3, 7));
+        assert_eq!(a.len(), 7);
+        assert_eq!(Interval::single(4u8).len(), 1);
+        assert!(a.contains(2) && a.contains(8) && !a.contains(9));
+        assert!(a.contains_interval(&b) && !b.contains_interval(&a));
+        assert_eq!(a.intersection(&b), Some(b));
+        assert_eq!(a.intersection(&Interval::new(9, 10)), None);
+        assert!(!a.overlaps(&Interval::new(9, 10)));
+        assert!(a.touches(&Interval::new(9, 10)));
+        assert_eq!(a.union(&Interval::new(9, 10)).to_string(), "2-10");
+        assert_eq!(a.union(&Interval::new(10, 11)).to_string(), "2-8,10-11");
+        assert_eq!(a.difference(&b).to_string(), "2-2,8-8");
+        assert_eq!(b.difference(&a).to_string(), "");
+        assert_eq!(a.difference(&Interval::new(-3, 4)).to_string(), "5-8");
+    }
+
+    #[test]
+    fn test_set() {
+        let mut intervals = set(&[(10, 12), (1, 3), (5, 6), (4, 4)]);
+        assert_eq!(intervals.to_string(), "1-6,10-12");
+        assert_eq!(intervals.covered(), 9);
+        assert!(intervals.contains(4) && intervals.contains(12));
+        assert!(!intervals.contains(7) && !intervals.contains(13));
+
+        intervals.insert(Interval::new(0, 20));
+        assert_eq!(intervals.intervals(), [Interval::new(0, 20)]);
+        intervals.remove(&Interval::new(5, 7));
+        intervals.remove(&Interval::new(15, 30));
+        assert_eq!(intervals.to_string(), "0-4,8-14");
+
+        let other = set(&[(3, 9), (12, 12), (14, 16)]);
+        assert_eq!(intervals.union(&other).to_string(), "0-16");
+        assert_eq!(
+            intervals.intersection(&other).to_string(),
+            "3-4,8-9,12-12,14-14"
+        );
+        assert_eq!(intervals.difference(&other).to_string(), "0-2,10-11,13-13");
+        assert!(IntervalSet::<i32>::new().intersection(&other).is_empty());
+    }
+
+    #[test]
+    fn test_bounds() {
+        let full = set(&[(i32::MIN, 0), (1, i32::MAX)]);
+        assert_eq!(full.intervals(), [Interval::new(i32::MIN, i32::MAX)]);
+        let mut ends = IntervalSet::from(Interval::new(u8::MIN, u8::MAX));
+        ends.remove(&Interval::new(1, 254));
+        assert_eq!(ends.to_string(), "0-0,255-255");
+        assert_eq!(ends.covered(), 2);
+    }
+}
@@ -1,3 +1,5 @@
+#![cfg_attr(feature = "simd", feature(portable_simd))]
+
 pub mod day1;
 pub mod day10;
 pub mod day11;
@@ -12,6 +14,7 @@ pub mod day6;
 pub mod day7;
 pub mod day8;
 pub mod day9;
+pub mod interval;
 
 use aoc_runner_derive::aoc_lib;
 